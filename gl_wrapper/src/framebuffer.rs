@@ -3,6 +3,7 @@ use std::{
     sync::atomic::{AtomicU32, Ordering},
 };
 
+use gl::types::GLenum;
 use log::{debug, error};
 
 static BOUND_FB: AtomicU32 = AtomicU32::new(0);
@@ -10,11 +11,25 @@ static BOUND_FB: AtomicU32 = AtomicU32::new(0);
 pub struct Framebuffer {
     fb_id: u32,
     color_buf: u32,
+    internal_format: GLenum,
     bound: bool,
 }
 
 impl Framebuffer {
     pub fn hdr(width: u32, height: u32) -> Self {
+        Self::with_format(width, height, gl::R11F_G11F_B10F)
+    }
+
+    pub fn ldr(width: u32, height: u32) -> Self {
+        Self::with_format(width, height, gl::RGBA8)
+    }
+
+    /// Two signed 32 bit float channels, for data read back by later passes.
+    pub fn rg32f(width: u32, height: u32) -> Self {
+        Self::with_format(width, height, gl::RG32F)
+    }
+
+    fn with_format(width: u32, height: u32, internal_format: GLenum) -> Self {
         unsafe {
             let mut fb_id = 0;
             gl::GenFramebuffers(1, ptr::addr_of_mut!(fb_id));
@@ -26,7 +41,7 @@ impl Framebuffer {
             gl::TexImage2D(
                 gl::TEXTURE_2D,
                 0,
-                gl::R11F_G11F_B10F as i32,
+                internal_format as i32,
                 width as i32,
                 height as i32,
                 0,
@@ -49,6 +64,7 @@ impl Framebuffer {
             Self {
                 fb_id,
                 color_buf,
+                internal_format,
                 bound: false,
             }
         }
@@ -67,7 +83,7 @@ impl Framebuffer {
             gl::TexImage2D(
                 gl::TEXTURE_2D,
                 0,
-                gl::R11F_G11F_B10F as i32,
+                self.internal_format as i32,
                 width as i32,
                 height as i32,
                 0,
@@ -79,6 +95,25 @@ impl Framebuffer {
         }
     }
 
+    /// Reads back the color attachment as tightly packed RGB floats.
+    ///
+    /// Rows are returned in OpenGL order, starting with the bottom row.
+    pub fn read_pixels(&self, width: u32, height: u32) -> Vec<f32> {
+        let mut data = vec![0.0_f32; width as usize * height as usize * 3];
+        unsafe {
+            gl::GetTextureImage(
+                self.color_buf,
+                0,
+                gl::RGB,
+                gl::FLOAT,
+                (data.len() * std::mem::size_of::<f32>()) as i32,
+                data.as_mut_ptr() as *mut _,
+            );
+        }
+
+        data
+    }
+
     pub fn clear(&self) {
         if self.bound {
             unsafe {
@@ -107,6 +142,7 @@ impl Framebuffer {
         let dummy_fb = Self {
             bound: true,
             color_buf: 0,
+            internal_format: 0,
             fb_id: 0,
         };
         draw(&dummy_fb);
@@ -127,9 +163,12 @@ impl Framebuffer {
 
 impl Drop for Framebuffer {
     fn drop(&mut self) {
+        // deleting a bound framebuffer reverts the binding to default one, and its id may get reused
+        BOUND_FB.compare_exchange(self.fb_id, 0, Ordering::SeqCst, Ordering::SeqCst).ok();
+
         unsafe {
-            gl::DeleteFramebuffers(1, self.fb_id as *const _);
-            gl::DeleteTextures(1, self.color_buf as *const _);
+            gl::DeleteFramebuffers(1, ptr::addr_of!(self.fb_id));
+            gl::DeleteTextures(1, ptr::addr_of!(self.color_buf));
         }
     }
 }
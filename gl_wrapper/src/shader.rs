@@ -36,6 +36,22 @@ impl Shader {
         };
     }
 
+    /// Sets consecutive elements of a uniform array, starting with the first one.
+    pub fn set_float_array_uniform<const N: usize>(&self, name: &str, float_vecs: &[[f32; N]]) {
+        unsafe {
+            let loc = self.get_uniform_location(name).unwrap();
+            let (count, ptr) = (float_vecs.len() as i32, float_vecs.as_ptr() as *const f32);
+
+            match N {
+                1 => gl::Uniform1fv(loc, count, ptr),
+                2 => gl::Uniform2fv(loc, count, ptr),
+                3 => gl::Uniform3fv(loc, count, ptr),
+                4 => gl::Uniform4fv(loc, count, ptr),
+                _ => panic!("invalid float vector size passed"),
+            }
+        };
+    }
+
     pub fn set_int_uniform<const N: usize>(&self, name: &str, int_vec: [i32; N]) {
         unsafe {
             let loc = self.get_uniform_location(name).unwrap();
@@ -10,9 +10,9 @@ pub struct Texture2d {
 impl Texture2d {
     pub fn new<S: TexStorage>(width: u32, height: u32, data: &[S], format: TextureFormat) -> Self {
         let expected_len = match format {
-            TextureFormat::Rgb => width * height * 3,
+            TextureFormat::Rgb | TextureFormat::RgbF32 => width * height * 3,
             TextureFormat::Rgba | TextureFormat::Srgba => width * height * 4,
-            TextureFormat::R8 => width * height,
+            TextureFormat::R8 | TextureFormat::RF32 => width * height,
         };
         assert_eq!(data.len(), expected_len as usize);
 
@@ -26,6 +26,8 @@ impl Texture2d {
                 TextureFormat::Rgb | TextureFormat::Rgba => gl::RGBA8,
                 TextureFormat::Srgba => gl::SRGB_ALPHA,
                 TextureFormat::R8 => gl::R8,
+                TextureFormat::RgbF32 => gl::RGB32F,
+                TextureFormat::RF32 => gl::R32F,
             };
 
             gl::TexStorage2D(gl::TEXTURE_2D, 1, int_format, width as i32, height as i32);
@@ -47,6 +49,16 @@ impl Texture2d {
         }
     }
 
+    /// Sets the same `filter` for minification and magnification and the same `wrap` mode on both axes.
+    pub fn set_sampling(&self, filter: GLenum, wrap: GLenum) {
+        unsafe {
+            gl::TextureParameteri(self.tex_id, gl::TEXTURE_MIN_FILTER, filter as i32);
+            gl::TextureParameteri(self.tex_id, gl::TEXTURE_MAG_FILTER, filter as i32);
+            gl::TextureParameteri(self.tex_id, gl::TEXTURE_WRAP_S, wrap as i32);
+            gl::TextureParameteri(self.tex_id, gl::TEXTURE_WRAP_T, wrap as i32);
+        }
+    }
+
     pub fn bind(&self, unit: u8) {
         unsafe {
             gl::ActiveTexture(gl::TEXTURE0 + unit as u32);
@@ -67,14 +79,16 @@ pub enum TextureFormat {
     Rgba,
     Srgba,
     R8,
+    RgbF32,
+    RF32,
 }
 
 impl From<TextureFormat> for GLenum {
     fn from(tf: TextureFormat) -> Self {
         match tf {
-            TextureFormat::Rgb => gl::RGB,
+            TextureFormat::Rgb | TextureFormat::RgbF32 => gl::RGB,
             TextureFormat::Rgba | TextureFormat::Srgba => gl::RGBA,
-            TextureFormat::R8 => gl::RED,
+            TextureFormat::R8 | TextureFormat::RF32 => gl::RED,
         }
     }
 }
@@ -0,0 +1,13 @@
+layout (binding = 0) uniform sampler2D background;
+// centered part of the plate visible in the frame
+uniform vec2 visible_fraction = vec2(1.0);
+
+layout (location = 0) in vec2 uv;
+
+out vec4 FragColor;
+
+void main() {
+    // plate rows are stored from top to bottom
+    vec2 plate_uv = (vec2(uv.x, 1.0 - uv.y) - 0.5) * visible_fraction + 0.5;
+    FragColor = vec4(texture(background, plate_uv).rgb, 1.0);
+}
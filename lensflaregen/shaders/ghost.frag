@@ -0,0 +1,8 @@
+layout (location = 0) in float colorInterp;
+
+// edge factor, premultiplied by the coverage so filtering at the rim stays correct
+out vec2 FragColor;
+
+void main() {
+    FragColor = vec2(colorInterp, 1.0);
+}
@@ -0,0 +1,10 @@
+layout (location = 0) in vec2 position;
+layout (location = 1) in float vertColor;
+
+layout (location = 0) out float colorInterp;
+
+// the aperture fan fills its tile of the shape atlas, ghosts are placed on screen by `ghosts.frag`
+void main() {
+    colorInterp = vertColor;
+    gl_Position = vec4(position, 0.0, 1.0);
+}
@@ -0,0 +1,140 @@
+// every ghost of a light in a single pass, each one evaluated at the dispersed sample positions
+#define MAX_GHOSTS 32
+
+#define KIND_DISC 1
+#define KIND_RING 2
+#define KIND_STREAK 3
+#define KIND_HALO 4
+
+layout(binding = 1) uniform sampler2D shape_atlas;
+layout(binding = 2) uniform sampler2D noise;
+layout(binding = 3) uniform sampler2D aperture_image;
+// color of every wavelength in the sampled range
+layout(binding = 5) uniform sampler2D spectrum;
+
+uniform int samples = 8;
+uniform vec2 res = vec2(1280.0 / 64.0, 720.0 / 64.0);
+uniform vec2 jitter_offset;
+uniform int ghost_count;
+uniform float shape_tiles = 1.0;
+// shape every aperture ghost by the aperture image instead of the geometry
+uniform int use_aperture_image = 0;
+
+// color times intensity, dispersion
+uniform vec4 ghost_color[MAX_GHOSTS];
+// from screen space around the ghost to the unit aperture, column major
+uniform vec4 ghost_transform[MAX_GHOSTS];
+// ghost position, distortion, dispersion from the ghost center instead of the image center
+uniform vec4 ghost_position[MAX_GHOSTS];
+// kind, tile in the shape atlas or -1 for a circle, two kind parameters
+uniform vec4 ghost_shape[MAX_GHOSTS];
+
+layout(location = 0) in vec2 uvInterp;
+
+out vec3 FragColor;
+
+// the aperture fan of `tile`, as edge factor and coverage
+vec2 shape_value(float tile, vec2 pos) {
+    float texel = 0.5 / float(textureSize(shape_atlas, 0).y);
+    vec2 uv = clamp(pos * 0.5 + 0.5, texel, 1.0 - texel);
+    return texture(shape_atlas, vec2((tile + uv.x) / shape_tiles, uv.y)).rg;
+}
+
+float ghost_value(vec4 shape, vec2 pos) {
+    if (any(greaterThan(abs(pos), vec2(1.0)))) {
+        return 0.0;
+    }
+
+    int kind = int(shape.x);
+    float center = length(pos);
+
+    if (kind == KIND_STREAK) {
+        float along = pow(max(1.0 - abs(pos.x), 0.0), shape.w);
+        return along * exp(-pow(pos.y / shape.z, 2.0));
+    }
+    if (kind == KIND_HALO) {
+        float peak = max(1.0 - 2.0 * shape.z, 0.0);
+        return exp(-pow((center - peak) / shape.z, 2.0));
+    }
+
+    float edgeFactor;
+    float mask = 1.0;
+    if (use_aperture_image == 1) {
+        mask = texture(aperture_image, vec2(pos.x, -pos.y) * 0.5 + 0.5).r;
+        edgeFactor = 0.0;
+    } else if (shape.y < 0.0) {
+        if (center > 1.0) {
+            return 0.0;
+        }
+        edgeFactor = center;
+    } else {
+        vec2 fan = shape_value(shape.y, pos);
+        mask = fan.y;
+        edgeFactor = fan.x / max(fan.y, 0.0001);
+    }
+
+    float edge;
+    if (kind == KIND_DISC) {
+        edge = 1.0 - smoothstep(1.0 - max(shape.z, 0.001), 1.0, edgeFactor);
+    } else if (kind == KIND_RING) {
+        // aperture images have no rim, the ring follows the unit circle
+        float rim = use_aperture_image == 1 ? center : edgeFactor;
+        edge = 1.0 - smoothstep(0.0, shape.z * 0.5, abs(rim - (1.0 - shape.z * 0.5)));
+    } else if (shape.z < 1.0) {
+        edge = 1.0 - pow(edgeFactor, 40.0) - gauss(center, 0.0, 0.3) * shape.z;
+    } else {
+        edge = 1.0 - pow(edgeFactor, 40.0) - gauss(pow(center, shape.z), 0.0, 0.3);
+    }
+
+    // the ghosts used to be drawn into an unsigned buffer first
+    return max(edge * mask, 0.0);
+}
+
+void main() {
+    float pixel_offset = texture(noise, uvInterp * res + jitter_offset).r;
+    vec2 moved = uvInterp - 0.5;
+    float moved_sq = dot(moved, moved);
+
+    float samples_f = float(samples);
+    float delta = 1.0 / samples_f;
+
+    // every channel is divided by its total weight, so dispersion keeps the ghost color
+    vec3 weight_sum = vec3(0.0);
+    for (int i = 0; i < samples; ++i) {
+        weight_sum += texture(spectrum, vec2((pixel_offset + float(i)) * delta, 0.5)).rgb;
+    }
+
+    vec3 color = vec3(0.0);
+    for (int g = 0; g < ghost_count; ++g) {
+        vec4 position = ghost_position[g];
+        float dispersion = ghost_color[g].w;
+        mat2 to_ghost = mat2(ghost_transform[g]);
+
+        vec2 pixel_distortion = uvInterp + moved * moved_sq * -position.z;
+        vec2 scale_center = position.w > 0.5 ? position.xy * 0.5 + 0.5 : vec2(0.5);
+
+        // the samples lie on a segment, skip ghosts it can't reach
+        vec2 first = to_ghost * (((pixel_distortion - scale_center) * (1.0 - dispersion) + scale_center) * 2.0 - 1.0 - position.xy);
+        vec2 last = to_ghost * (((pixel_distortion - scale_center) * (1.0 + dispersion) + scale_center) * 2.0 - 1.0 - position.xy);
+        if (any(greaterThan(min(first, last), vec2(1.0))) || any(lessThan(max(first, last), vec2(-1.0)))) {
+            continue;
+        }
+
+        vec3 ghost = vec3(0.0);
+        float x = pixel_offset / samples_f;
+        for (int i = 0; i < samples; ++i) {
+            float sample_dispersion = ((x * 2.0) - 1.0) * dispersion + 1.0;
+            vec2 uv = (pixel_distortion - scale_center) * sample_dispersion + scale_center;
+
+            // a wavelength sampled at a smaller scale draws a larger image, its energy is spread over that area
+            float area = sample_dispersion * sample_dispersion;
+            ghost += ghost_value(ghost_shape[g], to_ghost * (uv * 2.0 - 1.0 - position.xy)) * texture(spectrum, vec2(x, 0.5)).rgb * area;
+
+            x += delta;
+        }
+
+        color += ghost * ghost_color[g].rgb;
+    }
+
+    FragColor = color / max(weight_sum, vec3(1e-6));
+}
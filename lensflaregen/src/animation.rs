@@ -0,0 +1,321 @@
+//! Keyframe animation of [`Effect`] parameters.
+//!
+//! Time is measured in frames. Tracks without keyframes leave the animated value untouched.
+
+use serde::{Deserialize, Serialize};
+
+use crate::effect::Effect;
+
+/// A value that can be blended between two keyframes.
+pub trait Animatable: Copy {
+    fn lerp(self, other: Self, t: f32) -> Self;
+}
+
+impl Animatable for f32 {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl<const N: usize> Animatable for [f32; N] {
+    fn lerp(mut self, other: Self, t: f32) -> Self {
+        for (a, b) in self.iter_mut().zip(&other) {
+            *a = a.lerp(*b, t);
+        }
+        self
+    }
+}
+
+/// How the value moves from a keyframe towards the next one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum Interpolation {
+    /// Holds the value until the next keyframe.
+    Constant,
+    #[default]
+    Linear,
+    /// Easing curve with the control points `(x1, y1, x2, y2)`, as CSS `cubic-bezier`.
+    /// Both `x` values have to be in `[0, 1]`.
+    Bezier([f32; 4]),
+}
+
+impl Interpolation {
+    pub const EASE_IN_OUT: Self = Interpolation::Bezier([0.42, 0.0, 0.58, 1.0]);
+
+    fn progress(&self, x: f32) -> f32 {
+        match *self {
+            Interpolation::Constant => 0.0,
+            Interpolation::Linear => x,
+            Interpolation::Bezier([x1, y1, x2, y2]) => {
+                // x(s) is monotonic for x handles in [0, 1], so bisection always converges
+                let (mut lo, mut hi) = (0.0, 1.0);
+                for _ in 0..32 {
+                    let mid = 0.5 * (lo + hi);
+                    match cubic_bezier(x1, x2, mid) < x {
+                        true => lo = mid,
+                        false => hi = mid,
+                    }
+                }
+
+                cubic_bezier(y1, y2, 0.5 * (lo + hi))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Keyframe<T> {
+    pub time: f32,
+    pub value: T,
+    #[serde(default)]
+    pub interpolation: Interpolation,
+}
+
+impl<T> Keyframe<T> {
+    pub fn new(time: f32, value: T, interpolation: Interpolation) -> Self {
+        Self { time, value, interpolation }
+    }
+}
+
+/// Keyframes of a single parameter, ordered by time.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Track<T> {
+    pub keys: Vec<Keyframe<T>>,
+}
+
+impl<T: Animatable> Track<T> {
+    pub fn new(mut keys: Vec<Keyframe<T>>) -> Self {
+        keys.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Self { keys }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Value at `time`, held constant before the first and after the last keyframe.
+    pub fn sample(&self, time: f32) -> Option<T> {
+        let first = self.keys.first()?;
+        if time <= first.time {
+            return Some(first.value);
+        }
+
+        let next = self.keys.partition_point(|key| key.time <= time);
+        let prev = &self.keys[next - 1];
+        match self.keys.get(next) {
+            Some(next) => {
+                let x = (time - prev.time) / (next.time - prev.time);
+                Some(prev.value.lerp(next.value, prev.interpolation.progress(x)))
+            }
+            None => Some(prev.value),
+        }
+    }
+
+    fn apply(&self, value: &mut T, time: f32) {
+        if let Some(sampled) = self.sample(time) {
+            *value = sampled;
+        }
+    }
+}
+
+impl<T> Default for Track<T> {
+    fn default() -> Self {
+        Self { keys: Vec::new() }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FlareTracks {
+    #[serde(skip_serializing_if = "Track::is_empty")]
+    pub color: Track<[f32; 4]>,
+    #[serde(skip_serializing_if = "Track::is_empty")]
+    pub intensity: Track<f32>,
+    #[serde(skip_serializing_if = "Track::is_empty")]
+    pub size: Track<f32>,
+    #[serde(skip_serializing_if = "Track::is_empty")]
+    pub ray_intensity: Track<f32>,
+    #[serde(skip_serializing_if = "Track::is_empty")]
+    pub rotation: Track<f32>,
+}
+
+impl FlareTracks {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+/// Tracks of the light with the same index in [`Effect::lights`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LightTracks {
+    /// Light position as `[x, y]`.
+    #[serde(skip_serializing_if = "Track::is_empty")]
+    pub position: Track<[f32; 2]>,
+    #[serde(skip_serializing_if = "Track::is_empty")]
+    pub intensity: Track<f32>,
+    #[serde(skip_serializing_if = "Track::is_empty")]
+    pub tint: Track<[f32; 3]>,
+}
+
+/// Tracks of the ghost with the same index in [`Effect::ghosts`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GhostTracks {
+    #[serde(skip_serializing_if = "Track::is_empty")]
+    pub color: Track<[f32; 4]>,
+    #[serde(skip_serializing_if = "Track::is_empty")]
+    pub offset: Track<f32>,
+    #[serde(skip_serializing_if = "Track::is_empty")]
+    pub perpendicular_offset: Track<f32>,
+    #[serde(skip_serializing_if = "Track::is_empty")]
+    pub size: Track<f32>,
+    #[serde(skip_serializing_if = "Track::is_empty")]
+    pub dispersion: Track<f32>,
+    #[serde(skip_serializing_if = "Track::is_empty")]
+    pub distortion: Track<f32>,
+    #[serde(skip_serializing_if = "Track::is_empty")]
+    pub intensity: Track<f32>,
+    #[serde(skip_serializing_if = "Track::is_empty")]
+    pub center_transparency: Track<f32>,
+    #[serde(skip_serializing_if = "Track::is_empty")]
+    pub aspect_ratio: Track<f32>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Animation {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub lights: Vec<LightTracks>,
+    /// Rotation of the ghost apertures.
+    #[serde(skip_serializing_if = "Track::is_empty")]
+    pub rotation: Track<f32>,
+    #[serde(skip_serializing_if = "FlareTracks::is_empty")]
+    pub flare: FlareTracks,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ghosts: Vec<GhostTracks>,
+}
+
+impl Animation {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Overwrites the animated parameters of `effect` with their values at `time`.
+    pub fn apply(&self, effect: &mut Effect, time: f32) {
+        for (tracks, light) in self.lights.iter().zip(&mut effect.lights) {
+            if let Some([x, y]) = tracks.position.sample(time) {
+                light.set_position((x, y));
+            }
+            tracks.intensity.apply(&mut light.intensity, time);
+            tracks.tint.apply(&mut light.tint, time);
+        }
+        self.rotation.apply(&mut effect.rotation, time);
+
+        let flare = &mut effect.flare;
+        self.flare.color.apply(&mut flare.color, time);
+        self.flare.intensity.apply(&mut flare.intensity, time);
+        self.flare.size.apply(&mut flare.size, time);
+        self.flare.ray_intensity.apply(&mut flare.ray_intensity, time);
+        self.flare.rotation.apply(&mut flare.rotation, time);
+
+        for (tracks, ghost) in self.ghosts.iter().zip(&mut effect.ghosts) {
+            tracks.color.apply(&mut ghost.color, time);
+            tracks.offset.apply(&mut ghost.offset, time);
+            tracks.perpendicular_offset.apply(&mut ghost.perpendicular_offset, time);
+            tracks.size.apply(&mut ghost.size, time);
+            tracks.dispersion.apply(&mut ghost.dispersion, time);
+            tracks.distortion.apply(&mut ghost.distortion, time);
+            tracks.intensity.apply(&mut ghost.intensity, time);
+            tracks.center_transparency.apply(&mut ghost.center_transparency, time);
+            tracks.aspect_ratio.apply(&mut ghost.aspect_ratio, time);
+        }
+    }
+
+    /// Copy of `effect` with the animated parameters sampled at `time`.
+    pub fn sample(&self, effect: &Effect, time: f32) -> Effect {
+        let mut effect = effect.clone();
+        self.apply(&mut effect, time);
+        effect
+    }
+}
+
+// one dimensional cubic bezier with the end points fixed at 0 and 1
+fn cubic_bezier(p1: f32, p2: f32, s: f32) -> f32 {
+    let inv = 1.0 - s;
+    3.0 * inv * inv * s * p1 + 3.0 * inv * s * s * p2 + s * s * s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTERPOLATIONS: [Interpolation; 3] = [Interpolation::Constant, Interpolation::Linear, Interpolation::EASE_IN_OUT];
+
+    fn track(interpolation: Interpolation) -> Track<f32> {
+        Track::new(vec![
+            Keyframe::new(10.0, 1.0, interpolation),
+            Keyframe::new(20.0, 3.0, interpolation),
+            Keyframe::new(30.0, -1.0, interpolation),
+        ])
+    }
+
+    #[test]
+    fn empty_track_has_no_value() {
+        assert_eq!(Track::<f32>::default().sample(0.0), None);
+    }
+
+    #[test]
+    fn holds_first_value_before_first_key() {
+        for interpolation in INTERPOLATIONS {
+            assert_eq!(track(interpolation).sample(-5.0), Some(1.0), "{:?}", interpolation);
+            assert_eq!(track(interpolation).sample(9.9), Some(1.0), "{:?}", interpolation);
+        }
+    }
+
+    #[test]
+    fn holds_last_value_after_last_key() {
+        for interpolation in INTERPOLATIONS {
+            assert_eq!(track(interpolation).sample(30.1), Some(-1.0), "{:?}", interpolation);
+            assert_eq!(track(interpolation).sample(1000.0), Some(-1.0), "{:?}", interpolation);
+        }
+    }
+
+    #[test]
+    fn exact_on_keys() {
+        for interpolation in INTERPOLATIONS {
+            let track = track(interpolation);
+            for key in &track.keys {
+                assert_eq!(track.sample(key.time), Some(key.value), "{:?} at {}", interpolation, key.time);
+            }
+        }
+    }
+
+    #[test]
+    fn between_keys() {
+        let close = |a: Option<f32>, b: f32| (a.unwrap() - b).abs() < 1e-4;
+
+        let constant = track(Interpolation::Constant);
+        assert_eq!(constant.sample(19.9), Some(1.0));
+        assert_eq!(constant.sample(25.0), Some(3.0));
+
+        let linear = track(Interpolation::Linear);
+        assert!(close(linear.sample(12.5), 1.5));
+        assert!(close(linear.sample(25.0), 1.0));
+
+        // the symmetric ease passes the middle at half the value, slower than linear near the keys
+        let ease = track(Interpolation::EASE_IN_OUT);
+        assert!(close(ease.sample(15.0), 2.0));
+        assert!(ease.sample(12.0).unwrap() < linear.sample(12.0).unwrap());
+        assert!(ease.sample(18.0).unwrap() > linear.sample(18.0).unwrap());
+    }
+
+    #[test]
+    fn bezier_progress_follows_handles() {
+        let linear = Interpolation::Bezier([0.25, 0.25, 0.75, 0.75]);
+        for x in [0.0, 0.1, 0.5, 0.9, 1.0] {
+            assert!((linear.progress(x) - x).abs() < 1e-4, "{}", x);
+        }
+    }
+}
@@ -0,0 +1,51 @@
+//! Grayscale aperture images, for ghosts shaped like the iris of a specific lens.
+
+use std::path::Path;
+
+use crate::{
+    background,
+    sampling::{self, Edge},
+    LfgError,
+};
+
+/// Aperture transmission from 0 to 1, rows ordered from top to bottom.
+///
+/// The image is stretched over the square around the unit aperture, it replaces the aperture geometry of every ghost.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApertureImage {
+    width: u32,
+    height: u32,
+    pixels: Vec<f32>,
+}
+
+impl ApertureImage {
+    pub fn new(width: u32, height: u32, pixels: Vec<f32>) -> Result<Self, LfgError> {
+        if width == 0 || height == 0 || pixels.len() != width as usize * height as usize {
+            return Err(LfgError::InvalidEffectValue("aperture image size".into()));
+        }
+
+        Ok(Self { width, height, pixels })
+    }
+
+    /// Loads the luminance of an image supported by `image`, without any color conversion.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, LfgError> {
+        let (width, height, rgba) = background::load_rgba(path.as_ref())?;
+        let pixels = rgba.iter().map(|[r, g, b, _]| (0.2126 * r + 0.7152 * g + 0.0722 * b).clamp(0.0, 1.0)).collect();
+
+        Self::new(width, height, pixels)
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    pub fn pixels(&self) -> &[f32] {
+        &self.pixels
+    }
+
+    // bilinear, clamped to the edges, `(0, 0)` being the top left corner
+    pub(crate) fn sample(&self, u: f32, v: f32) -> f32 {
+        let width = self.width as usize;
+        sampling::bilinear((width, self.height as usize), (u, v), Edge::Clamp, |x, y| self.pixels[y * width + x])
+    }
+}
@@ -0,0 +1,124 @@
+//! Background plates the flare is added onto, before tonemapping.
+
+use std::{fs::File, io::BufReader, path::Path};
+
+use image::{codecs::hdr::HdrDecoder, DynamicImage};
+
+use crate::{
+    sampling::{self, Edge},
+    LfgError,
+};
+
+/// Scene-linear RGB image, with rows ordered from top to bottom.
+///
+/// The plate covers the whole frame, it is scaled uniformly and the overhanging part is cropped.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Background {
+    width: u32,
+    height: u32,
+    pixels: Vec<f32>,
+}
+
+impl Background {
+    pub fn new(width: u32, height: u32, pixels: Vec<f32>) -> Result<Self, LfgError> {
+        if width == 0 || height == 0 || pixels.len() != width as usize * height as usize * 3 {
+            return Err(LfgError::InvalidEffectValue("background size".into()));
+        }
+
+        Ok(Self { width, height, pixels })
+    }
+
+    /// Loads `.exr` and `.hdr` files as scene-linear data, any other format supported by `image`
+    /// is treated as sRGB encoded.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, LfgError> {
+        let path = path.as_ref();
+        let extension = path.extension().map(|ext| ext.to_string_lossy().to_lowercase()).unwrap_or_default();
+
+        match extension.as_str() {
+            "exr" => Self::load_exr(path),
+            "hdr" => Self::load_hdr(path),
+            _ => {
+                let (width, height, rgba) = load_rgba(path)?;
+                let pixels = rgba.iter().flat_map(|p| [p[0], p[1], p[2]]).map(decode_srgb).collect();
+                Self::new(width, height, pixels)
+            }
+        }
+    }
+
+    fn load_exr(path: &Path) -> Result<Self, LfgError> {
+        let image = exr::prelude::read_first_rgba_layer_from_file(
+            path,
+            |size, _| (size.width(), vec![0.0; size.width() * size.height() * 3]),
+            |(width, pixels): &mut (usize, Vec<f32>), pos, (r, g, b, _a): (f32, f32, f32, f32)| {
+                let idx = (pos.y() * *width + pos.x()) * 3;
+                pixels[idx..idx + 3].copy_from_slice(&[r, g, b]);
+            },
+        )?;
+
+        let size = image.layer_data.size;
+        let (_, pixels) = image.layer_data.channel_data.pixels;
+        Self::new(size.width() as u32, size.height() as u32, pixels)
+    }
+
+    fn load_hdr(path: &Path) -> Result<Self, LfgError> {
+        let decoder = HdrDecoder::new(BufReader::new(File::open(path)?))?;
+        let meta = decoder.metadata();
+        let pixels = decoder.read_image_hdr()?.iter().flat_map(|p| p.0).collect();
+
+        Self::new(meta.width, meta.height, pixels)
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    pub fn pixels(&self) -> &[f32] {
+        &self.pixels
+    }
+
+    /// Bilinearly filtered color at `(u, v)`, `(0, 0)` being the top left corner, clamped to the edges.
+    pub fn sample(&self, u: f32, v: f32) -> [f32; 3] {
+        let width = self.width as usize;
+        sampling::bilinear((width, self.height as usize), (u, v), Edge::Clamp, |x, y| {
+            let idx = (y * width + x) * 3;
+            [self.pixels[idx], self.pixels[idx + 1], self.pixels[idx + 2]]
+        })
+    }
+}
+
+/// Centered part of a plate visible in a frame, as a fraction of the plate width and height.
+pub fn visible_fraction((width, height): (u32, u32), (frame_width, frame_height): (u32, u32)) -> (f32, f32) {
+    let aspect = width as f32 / height as f32;
+    let frame_aspect = frame_width as f32 / frame_height as f32;
+
+    match aspect > frame_aspect {
+        true => (frame_aspect / aspect, 1.0),
+        false => (1.0, aspect / frame_aspect),
+    }
+}
+
+/// Loads an image supported by `image` as RGBA values in `[0, 1]`, without any color conversion.
+pub(crate) fn load_rgba(path: &Path) -> Result<(u32, u32, Vec<[f32; 4]>), LfgError> {
+    // `into_rgba16` shifts 8 bit values instead of scaling them, so only 16 bit sources go that way
+    let pixels = match image::open(path)? {
+        img @ (DynamicImage::ImageLuma16(_) | DynamicImage::ImageLumaA16(_) | DynamicImage::ImageRgb16(_) | DynamicImage::ImageRgba16(_)) => {
+            let img = img.into_rgba16();
+            let pixels = img.pixels().map(|p| p.0.map(|v| v as f32 / 65535.0)).collect();
+            (img.width(), img.height(), pixels)
+        }
+        img => {
+            let img = img.into_rgba8();
+            let pixels = img.pixels().map(|p| p.0.map(|v| v as f32 / 255.0)).collect();
+            (img.width(), img.height(), pixels)
+        }
+    };
+
+    Ok(pixels)
+}
+
+fn decode_srgb(v: f32) -> f32 {
+    match v <= 0.04045 {
+        true => v / 12.92,
+        false => ((v + 0.055) / 1.055).powf(2.4),
+    }
+}
@@ -0,0 +1,531 @@
+//! Pure Rust port of the GL draw passes, for machines without a GPU and as a reference for the GL output.
+//!
+//! Every function mirrors one shader, so changes to `shaders/` should be mirrored here.
+
+use std::thread;
+
+use cgmath::{prelude::*, vec2, Matrix2, Rad, Vector2};
+
+use crate::{
+    aperture::ApertureImage,
+    background::{self, Background},
+    effect::{ApertureShape, Effect},
+    flare::{Flare, FlareStyle, MAX_STREAKS},
+    ghost,
+    light::Light,
+    renderer::{NOISE_BYTES, SHAPE_TILE},
+    sampling::{self, mix, Edge},
+    spectrum,
+    starburst::STARBURST_SIZE,
+    LfgError,
+};
+
+// kinds as numbered in `ghosts.frag`, the indices of `GhostKind::NAMES`
+const KIND_DISC: usize = 1;
+const KIND_RING: usize = 2;
+const KIND_STREAK: usize = 3;
+const KIND_HALO: usize = 4;
+
+// same approximations as `common.glsl`, so both backends agree
+#[allow(clippy::approx_constant)]
+const E: f32 = 2.71828;
+#[allow(clippy::approx_constant)]
+const PI: f32 = 3.14159;
+
+/// Renders effects into memory on the CPU, matching [`crate::renderer::Renderer`] within a small tolerance.
+pub struct CpuRenderer {
+    noise: Plane,
+    background: Option<Background>,
+    aperture_image: Option<ApertureImage>,
+}
+
+impl CpuRenderer {
+    pub fn new() -> Result<Self, LfgError> {
+        let img = image::load_from_memory(NOISE_BYTES)?.to_rgb8();
+        let data = img.pixels().map(|p| [p[0] as f32 / 255.0, p[1] as f32 / 255.0, p[2] as f32 / 255.0]).collect();
+
+        let noise = Plane {
+            width: img.width() as usize,
+            height: img.height() as usize,
+            data,
+        };
+
+        Ok(Self {
+            noise,
+            background: None,
+            aperture_image: None,
+        })
+    }
+
+    /// Sets the plate added under the effect, `None` renders onto black.
+    pub fn set_background(&mut self, background: Option<Background>) {
+        self.background = background;
+    }
+
+    /// Sets the aperture image every ghost is shaped by, `None` goes back to the aperture geometry.
+    pub fn set_aperture_image(&mut self, image: Option<ApertureImage>) {
+        self.aperture_image = image;
+    }
+
+    /// Renders `effect` and returns the linear HDR buffer.
+    ///
+    /// The buffer holds `width * height` RGB triplets, with rows ordered from top to bottom.
+    pub fn render(&self, effect: &Effect, (width, height): (u32, u32)) -> Vec<f32> {
+        if width == 0 || height == 0 {
+            return Vec::new();
+        }
+
+        let mut main = Plane::new(width as usize, height as usize);
+        let aperture_image = self.aperture_image.is_some();
+
+        // apertures in the order of their tiles, like `Renderer::shape_tiles`
+        let mut shapes = Vec::new();
+        for ghost in effect.visible_ghosts() {
+            let shape = effect.ghost_aperture(ghost, aperture_image);
+            if shape != ApertureShape::Circular && !shapes.contains(&shape) {
+                shapes.push(shape);
+            }
+        }
+        let tiles: Vec<_> = shapes.iter().map(|shape| draw_shape_tile(*shape)).collect();
+
+        let spectrum = effect.spectrum.table();
+        // generated on every call, unlike the GL renderer which keeps it until the aperture changes
+        let starburst = match effect.flare.style {
+            FlareStyle::Starburst => Some(Plane {
+                width: STARBURST_SIZE,
+                height: STARBURST_SIZE,
+                data: effect.flare.starburst.generate(effect.aperture_shape, &effect.spectrum),
+            }),
+            _ => None,
+        };
+
+        for light in &effect.lights {
+            let uniforms: Vec<_> = effect
+                .visible_ghosts()
+                .filter_map(|ghost| {
+                    let shape = effect.ghost_aperture(ghost, aperture_image);
+                    let tile = shapes.iter().position(|tile| *tile == shape).map_or(-1.0, |idx| idx as f32);
+                    ghost.pass_uniforms(light, effect.rotation, (width, height), tile)
+                })
+                .collect();
+
+            self.draw_ghosts(&mut main, &uniforms, &tiles, effect.samples, &spectrum);
+            self.draw_flare(&mut main, &effect.flare, light, effect, starburst.as_ref());
+        }
+
+        if let Some(background) = &self.background {
+            draw_background(&mut main, background);
+        }
+
+        main.data.chunks_exact(main.width).rev().flatten().flatten().copied().collect()
+    }
+
+    // `ghosts.frag`, every ghost of a light evaluated at the dispersed sample positions
+    fn draw_ghosts(&self, main: &mut Plane, ghosts: &[[[f32; 4]; 4]], tiles: &[Plane], samples: u16, spectrum: &[[f32; 3]]) {
+        let (width, height) = (main.width as f32, main.height as f32);
+        let res = vec2(width / 128.0, height / 128.0);
+        let samples_f = samples as f32;
+        let delta = 1.0 / samples_f;
+
+        for_each_row(main, |y, row| {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                let uv = vec2((x as f32 + 0.5) / width, (y as f32 + 0.5) / height);
+
+                let pixel_offset = self.noise.sample(uv.x * res.x, uv.y * res.y)[0];
+                let moved = uv - vec2(0.5, 0.5);
+                let moved_sq = moved.dot(moved);
+
+                // every channel is divided by its total weight, so dispersion keeps the ghost color
+                let mut weight_sum = [0.0; 3];
+                for idx in 0..samples {
+                    let weights = spectrum::sample_table(spectrum, (pixel_offset + idx as f32) * delta);
+                    weight_sum.iter_mut().zip(&weights).for_each(|(sum, weight)| *sum += weight);
+                }
+
+                let mut color = [0.0; 3];
+                for [ghost_color, transform, position, shape] in ghosts {
+                    let dispersion = ghost_color[3];
+                    let to_ghost = Matrix2::new(transform[0], transform[1], transform[2], transform[3]);
+                    let ghost_pos = vec2(position[0], position[1]);
+
+                    let pixel_distortion = uv + moved * moved_sq * -position[2];
+                    let scale_center = match position[3] > 0.5 {
+                        true => ghost_pos * 0.5 + vec2(0.5, 0.5),
+                        false => vec2(0.5, 0.5),
+                    };
+                    let to_aperture = |scale: f32| to_ghost * (((pixel_distortion - scale_center) * scale + scale_center) * 2.0 - vec2(1.0, 1.0) - ghost_pos);
+
+                    // the samples lie on a segment, skip ghosts it can't reach
+                    let (first, last) = (to_aperture(1.0 - dispersion), to_aperture(1.0 + dispersion));
+                    if first.x.min(last.x) > 1.0 || first.y.min(last.y) > 1.0 || first.x.max(last.x) < -1.0 || first.y.max(last.y) < -1.0 {
+                        continue;
+                    }
+
+                    let mut ghost = [0.0; 3];
+                    let mut sample_x = pixel_offset / samples_f;
+                    for _ in 0..samples {
+                        let sample_dispersion = ((sample_x * 2.0) - 1.0) * dispersion + 1.0;
+                        // a wavelength sampled at a smaller scale draws a larger image, its energy is spread over that area
+                        let area = sample_dispersion * sample_dispersion;
+                        let value = ghost_value(*shape, tiles, self.aperture_image.as_ref(), to_aperture(sample_dispersion)) * area;
+
+                        let weights = spectrum::sample_table(spectrum, sample_x);
+                        ghost.iter_mut().zip(&weights).for_each(|(ghost, weight)| *ghost += value * weight);
+
+                        sample_x += delta;
+                    }
+
+                    for ((color, ghost), ghost_color) in color.iter_mut().zip(&ghost).zip(ghost_color) {
+                        *color += ghost * ghost_color;
+                    }
+                }
+
+                for ((value, color), sum) in pixel.iter_mut().zip(&color).zip(&weight_sum) {
+                    *value += color / sum.max(1e-6);
+                }
+            }
+        });
+    }
+
+    // `flare.frag`
+    fn draw_flare(&self, main: &mut Plane, flare: &Flare, light: &Light, effect: &Effect, starburst: Option<&Plane>) {
+        let (width, height) = (main.width as f32, main.height as f32);
+        let color = light.apply_to(flare.color);
+        let aspect_ratio = width / height;
+        let blades = effect.aperture_shape.get_blade_count() as f32;
+        let blade_curvature = effect.aperture_shape.curvature();
+        let flare_rotation = flare.rotation_at(light, aspect_ratio);
+        let rotation = -(effect.aperture_shape.rotation() + flare_rotation);
+        let size = flare.size;
+        let texture_rotation = Matrix2::from_angle(Rad(flare_rotation)).transpose();
+        let starburst_rotation = Matrix2::from_angle(Rad(-flare_rotation));
+        let streak_directions: Vec<_> = flare
+            .streaks
+            .angles
+            .iter()
+            .take(MAX_STREAKS)
+            .map(|angle| vec2((angle + flare_rotation).cos(), (angle + flare_rotation).sin()))
+            .collect();
+
+        let rays = |distance: f32, norm_angle: f32| {
+            let angle = norm_angle * 2.0 * PI * blades + PI;
+            let distance_limit = (1.0 - distance).max(0.0);
+            let ray_centers = angle.cos().max(0.0).powf(8.0) * distance_limit;
+
+            ray_centers.powf(2.0)
+        };
+
+        for_each_row(main, |y, row| {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                let uv = vec2((x as f32 + 0.5) / width, (y as f32 + 0.5) / height);
+                let flare_base = vec2((uv.x - light.pos_x) * aspect_ratio, uv.y - light.pos_y);
+
+                let (dist, angle) = euler_to_polar(flare_base);
+                let angle = (angle + PI / 2.0 + rotation) / (2.0 * PI);
+
+                let noise_uv = texture_rotation * vec2(dist * 0.001, angle) * 5.0;
+                let rad_noise = self.noise.sample(noise_uv.x, noise_uv.y)[0];
+
+                let noise_ring_extrusion = mix((angle * 2.0 * PI * blades + PI).cos(), 1.0, 0.95);
+                let blade_count_to_ray_intensity = ((-blades + 18.0) / 12.0).clamp(0.0, 1.0) * (1.0 - blade_curvature);
+
+                let noise_ring_intensity = gauss(dist * noise_ring_extrusion / (size / 10.0), 0.21, 0.01);
+                let noise_ring = rad_noise * noise_ring_intensity;
+
+                let flare_value = match flare.style {
+                    FlareStyle::Anamorphic => {
+                        let anamorphic = &flare.anamorphic;
+                        let anam_ring = noise_ring * anamorphic.ring;
+                        let anam_flare = ((gauss(dist, 0.0, size / 200.0) + anam_ring) + gauss(dist, 0.0, size / 2000.0)) * flare.intensity;
+
+                        let ray_distort = 1.0 - anam_flare * 0.2;
+                        let ray_fade = (1.0 - (anamorphic.falloff * flare_base.x).abs()).max(0.0);
+
+                        let anam_ray_base = flare_base.y * ray_distort / ray_fade;
+                        let anam_ray = (1.0 - (anam_ray_base.abs() - anamorphic.thickness) / anamorphic.softness).clamp(0.0, 1.0) * flare.ray_intensity;
+
+                        let value = (anam_flare + anam_ray).max(anam_ray) * gauss(flare_base.x, 0.0, anamorphic.length);
+                        let to_tip = (flare_base.x.abs() / anamorphic.length).min(1.0);
+
+                        let mut out = [0.0; 3];
+                        for ((out, core), tip) in out.iter_mut().zip(&anamorphic.core_color).zip(&anamorphic.tip_color) {
+                            *out = value * mix(*core, *tip, to_tip);
+                        }
+                        out
+                    }
+                    FlareStyle::Glow => {
+                        let flare_gauss = gauss(dist, 0.0, size / 100.0);
+                        let halo = (1.0 - dist / flare.glow.radius).max(0.0).powf(flare.glow.falloff);
+
+                        [(flare_gauss * flare.intensity) + (halo * flare.ray_intensity); 3]
+                    }
+                    FlareStyle::Streaks => {
+                        let flare_gauss = gauss(dist, 0.0, size / 100.0);
+
+                        let streaks: f32 = streak_directions
+                            .iter()
+                            .map(|direction| {
+                                let along = flare_base.dot(*direction).abs();
+                                let across = (direction.x * flare_base.y - direction.y * flare_base.x).abs();
+
+                                let fade = (1.0 - along / flare.streaks.length).max(0.0);
+                                gauss(across / flare.streaks.thickness, 0.0, 1.0) * fade * fade
+                            })
+                            .sum();
+
+                        [(flare_gauss * flare.intensity) + (streaks * flare.ray_intensity); 3]
+                    }
+                    FlareStyle::Normal | FlareStyle::Starburst => {
+                        let flare_gauss = gauss(dist, 0.0, size / 100.0);
+                        let rays_value = match starburst {
+                            Some(starburst) => {
+                                let rotated = starburst_rotation * flare_base;
+                                let value = starburst.sample(rotated.x * 0.5 + 0.5, rotated.y * 0.5 + 0.5);
+                                value.map(|channel| channel * (1.0 - dist).max(0.0))
+                            }
+                            None => [mix(noise_ring, rays(dist, angle) * rad_noise, blade_count_to_ray_intensity); 3],
+                        };
+                        let ray_center = 2.0 * gauss(dist, 0.0, 0.02);
+
+                        rays_value.map(|rays_value| (flare_gauss * flare.intensity) + ((rays_value + ray_center) * flare.ray_intensity))
+                    }
+                };
+
+                for ((value, flare_value), color) in pixel.iter_mut().zip(&flare_value).zip(&color) {
+                    *value += flare_value * color;
+                }
+            }
+        });
+    }
+}
+
+/// Applies the `tonemap.frag` pass to a linear buffer and returns sRGB encoded bytes.
+pub fn tonemap(hdr: &[f32], tonemap: bool) -> Vec<u8> {
+    hdr.iter()
+        .map(|&value| {
+            let value = match tonemap {
+                true => 1.0 - (-value).exp(),
+                false => value,
+            };
+            let encoded = match value < 0.0031308 {
+                true => 12.92 * value,
+                false => 1.055 * value.powf(1.0 / 2.4) - 0.055,
+            };
+
+            (encoded.clamp(0.0, 1.0) * 255.0).round() as u8
+        })
+        .collect()
+}
+
+// `ghost.frag`, the aperture fan drawn into a tile of the shape atlas as edge factor and coverage
+fn draw_shape_tile(shape: ApertureShape) -> Plane {
+    let size = SHAPE_TILE as usize;
+    let mut tile = Plane::new(size, size);
+
+    // (pixel position, edge factor) for each vertex
+    let vertices: Vec<_> = ghost::ghost_geo_vertices(shape)
+        .chunks_exact(3)
+        .map(|v| (vec2((v[0] + 1.0) * 0.5 * size as f32, (v[1] + 1.0) * 0.5 * size as f32), v[2]))
+        .collect();
+
+    for i in 1..vertices.len() - 1 {
+        let (a, b, c) = (vertices[0], vertices[i], vertices[i + 1]);
+
+        let area = edge_function(a.0, b.0, c.0);
+        if area == 0.0 {
+            continue;
+        }
+
+        let min_x = a.0.x.min(b.0.x).min(c.0.x).floor().max(0.0) as usize;
+        let max_x = (a.0.x.max(b.0.x).max(c.0.x).ceil() as usize).min(size);
+        let min_y = a.0.y.min(b.0.y).min(c.0.y).floor().max(0.0) as usize;
+        let max_y = (a.0.y.max(b.0.y).max(c.0.y).ceil() as usize).min(size);
+
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let p = vec2(x as f32 + 0.5, y as f32 + 0.5);
+                let w_a = edge_function(b.0, c.0, p) / area;
+                let w_b = edge_function(c.0, a.0, p) / area;
+                let w_c = edge_function(a.0, b.0, p) / area;
+
+                if w_a < 0.0 || w_b < 0.0 || w_c < 0.0 {
+                    continue;
+                }
+
+                tile.data[y * size + x] = [a.1 * w_a + b.1 * w_b + c.1 * w_c, 1.0, 0.0];
+            }
+        }
+    }
+
+    tile
+}
+
+// the profile of one ghost in `ghosts.frag`, `shape` holding the kind, the shape tile and two kind parameters
+fn ghost_value(shape: [f32; 4], tiles: &[Plane], aperture_image: Option<&ApertureImage>, pos: Vector2<f32>) -> f32 {
+    if pos.x.abs() > 1.0 || pos.y.abs() > 1.0 {
+        return 0.0;
+    }
+
+    let [kind, tile, param_a, param_b] = shape;
+    let kind = kind as usize;
+    let center = pos.magnitude();
+
+    if kind == KIND_STREAK {
+        let along = (1.0 - pos.x.abs()).max(0.0).powf(param_b);
+        return along * (-(pos.y / param_a).powf(2.0)).exp();
+    }
+    if kind == KIND_HALO {
+        let peak = (1.0 - 2.0 * param_a).max(0.0);
+        return (-((center - peak) / param_a).powf(2.0)).exp();
+    }
+
+    let (edge_factor, mask) = match aperture_image {
+        Some(image) => (0.0, image.sample(pos.x * 0.5 + 0.5, 0.5 - pos.y * 0.5)),
+        None if tile < 0.0 => {
+            if center > 1.0 {
+                return 0.0;
+            }
+            (center, 1.0)
+        }
+        None => {
+            let tile = &tiles[tile as usize];
+            let texel = 0.5 / tile.height as f32;
+            let uv = (pos * 0.5 + vec2(0.5, 0.5)).map(|c| c.clamp(texel, 1.0 - texel));
+            let [edge, coverage, _] = tile.sample(uv.x, uv.y);
+            (edge / coverage.max(0.0001), coverage)
+        }
+    };
+
+    let edge = match kind {
+        KIND_DISC => 1.0 - smoothstep(1.0 - param_a.max(0.001), 1.0, edge_factor),
+        KIND_RING => {
+            // aperture images have no rim, the ring follows the unit circle
+            let rim = match aperture_image {
+                Some(_) => center,
+                None => edge_factor,
+            };
+            1.0 - smoothstep(0.0, param_a * 0.5, (rim - (1.0 - param_a * 0.5)).abs())
+        }
+        _ => match param_a < 1.0 {
+            true => 1.0 - edge_factor.powf(40.0) - gauss(center, 0.0, 0.3) * param_a,
+            false => 1.0 - edge_factor.powf(40.0) - gauss(center.powf(param_a), 0.0, 0.3),
+        },
+    };
+
+    // the ghosts used to be drawn into an unsigned buffer first
+    (edge * mask).max(0.0)
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn edge_function(a: Vector2<f32>, b: Vector2<f32>, p: Vector2<f32>) -> f32 {
+    (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x)
+}
+
+fn gauss(x: f32, center: f32, std_dev: f32) -> f32 {
+    E.powf(-((x - center).powf(2.0) / std_dev))
+}
+
+fn euler_to_polar(euler: Vector2<f32>) -> (f32, f32) {
+    let dist = euler.magnitude();
+    if dist == 0.0 {
+        return (0.0, 0.0);
+    }
+
+    let angle = match euler.y < 0.0 {
+        true => -(euler.x / dist).acos(),
+        false => (euler.x / dist).acos(),
+    };
+
+    (dist, angle)
+}
+
+/// RGB float image, with the bottom row first like GL textures.
+struct Plane {
+    width: usize,
+    height: usize,
+    data: Vec<[f32; 3]>,
+}
+
+impl Plane {
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            data: vec![[0.0; 3]; width * height],
+        }
+    }
+
+    /// Bilinear sample with repeat wrapping, same as `GL_LINEAR` and `GL_REPEAT` sampling.
+    fn sample(&self, u: f32, v: f32) -> [f32; 3] {
+        sampling::bilinear((self.width, self.height), (u, v), Edge::Repeat, |x, y| self.data[y * self.width + x])
+    }
+}
+
+// `background.frag`, adding the plate scaled to cover the frame
+fn draw_background(main: &mut Plane, background: &Background) {
+    let (width, height) = (main.width as f32, main.height as f32);
+    let (fraction_x, fraction_y) = background::visible_fraction(background.size(), (main.width as u32, main.height as u32));
+
+    for_each_row(main, |y, row| {
+        // plate rows are stored from top to bottom
+        let v = ((1.0 - (y as f32 + 0.5) / height) - 0.5) * fraction_y + 0.5;
+
+        for (x, pixel) in row.iter_mut().enumerate() {
+            let u = ((x as f32 + 0.5) / width - 0.5) * fraction_x + 0.5;
+            for (value, plate) in pixel.iter_mut().zip(&background.sample(u, v)) {
+                *value += plate;
+            }
+        }
+    });
+}
+
+/// Runs `draw` for every row of `plane` in parallel, passing the row index counted from the bottom.
+fn for_each_row<F: Fn(usize, &mut [[f32; 3]]) + Sync>(plane: &mut Plane, draw: F) {
+    if plane.data.is_empty() {
+        return;
+    }
+
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let rows_per_chunk = plane.height.div_ceil(threads);
+    let width = plane.width;
+    let draw = &draw;
+
+    thread::scope(|s| {
+        for (chunk_idx, chunk) in plane.data.chunks_mut(rows_per_chunk * width).enumerate() {
+            s.spawn(move || {
+                for (row_idx, row) in chunk.chunks_mut(width).enumerate() {
+                    draw(chunk_idx * rows_per_chunk + row_idx, row);
+                }
+            });
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ghost_rotation_leaves_flare_alone() {
+        let mut effect = Effect::new();
+        effect.ghosts.clear();
+        let renderer = CpuRenderer::new().unwrap();
+        let size = (64, 36);
+
+        let before = renderer.render(&effect, size);
+        effect.rotation += 1.3;
+        assert_eq!(before, renderer.render(&effect, size));
+    }
+
+    #[test]
+    fn empty_frame_renders_nothing() {
+        let renderer = CpuRenderer::new().unwrap();
+        for size in [(0, 36), (64, 0), (0, 0)] {
+            assert!(renderer.render(&Effect::new(), size).is_empty());
+        }
+    }
+}
@@ -0,0 +1,284 @@
+use std::{
+    convert::TryFrom,
+    hash::{Hash, Hasher},
+    num::NonZeroU8,
+};
+
+use cgmath::{Matrix2, Rad};
+use serde::{Deserialize, Serialize};
+
+use crate::{flare::Flare, ghost::Ghost, light::Light, occlusion, preset, renderer::Renderer, spectrum::Spectrum, LfgError};
+
+// size of the uniform arrays in `ghosts.frag`
+const MAX_GHOSTS_PER_PASS: usize = 32;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Effect {
+    pub flare: Flare,
+    pub ghosts: Vec<Ghost>,
+    /// Rotation of the ghost apertures in radians, the flare has its own.
+    pub rotation: f32,
+    pub aperture_shape: ApertureShape,
+    pub lights: Vec<Light>,
+    /// Radius of the light disc sampled from occlusion masks, relative to the frame height.
+    pub occlusion_radius: f32,
+    /// Wavelengths sampled for the dispersion of every ghost.
+    pub samples: u16,
+    pub spectrum: Spectrum,
+    pub tonemap: bool,
+}
+
+impl Effect {
+    pub fn new() -> Self {
+        Self {
+            flare: Flare::new(),
+            ghosts: vec![
+                Ghost { ..Default::default() },
+                Ghost {
+                    offset: 0.2,
+                    size: 5.0,
+                    color: [1.0, 0.5, 0.5, 1.0],
+                    ..Default::default()
+                },
+                Ghost {
+                    offset: -0.8,
+                    size: 20.0,
+                    ..Default::default()
+                },
+                Ghost {
+                    offset: -0.4,
+                    size: 15.0,
+                    ..Default::default()
+                },
+            ],
+            rotation: 0.2,
+            aperture_shape: ApertureShape::from_blade_count(8).unwrap(),
+            lights: vec![Light::new()],
+            occlusion_radius: occlusion::DEFAULT_RADIUS,
+            samples: 8,
+            spectrum: Spectrum::new(),
+            tonemap: true,
+        }
+    }
+
+    pub fn draw(&self, renderer: &mut Renderer) {
+        let aperture_image = renderer.aperture_image.is_some();
+        let ghosts: Vec<_> = self
+            .visible_ghosts()
+            .map(|ghost| (ghost, renderer.shape_tile(self.ghost_aperture(ghost, aperture_image))))
+            .collect();
+
+        let Renderer {
+            shader_lib,
+            main_hdr_buf: main_fb,
+            quad,
+            shape_atlas,
+            shape_tiles,
+            size,
+            frame_num,
+            ..
+        } = renderer;
+
+        let jitter_offset = match *frame_num % 4 {
+            0 => [0.0, 0.0],
+            1 => [0.5, 0.0],
+            2 => [0.5, 0.5],
+            3 => [0.0, 0.5],
+            _ => [0.0, 0.0],
+        };
+
+        // clear main frame
+        main_fb.draw_with(|fb| fb.clear());
+
+        for light in &self.lights {
+            let uniforms: Vec<_> = ghosts
+                .iter()
+                .filter_map(|(ghost, tile)| ghost.pass_uniforms(light, self.rotation, *size, *tile))
+                .collect();
+
+            // all ghosts of the light at once, in as few passes as the uniform arrays allow
+            for batch in uniforms.chunks(MAX_GHOSTS_PER_PASS) {
+                main_fb.draw_with(|_fb| {
+                    let shader = &shader_lib.ghosts;
+                    shader.bind();
+                    shader.set_float_uniform("res", [size.0 as f32 / 128.0, size.1 as f32 / 128.0]);
+                    shader.set_int_uniform("samples", [self.samples as i32]);
+                    shader.set_float_uniform("jitter_offset", jitter_offset);
+                    shader.set_float_uniform("shape_tiles", [shape_tiles.len().max(1) as f32]);
+                    shader.set_int_uniform("use_aperture_image", [aperture_image as i32]);
+                    shape_atlas.bind_as_color_texture(1);
+
+                    shader.set_int_uniform("ghost_count", [batch.len() as i32]);
+                    let field = |idx: usize| batch.iter().map(|uniforms| uniforms[idx]).collect::<Vec<_>>();
+                    shader.set_float_array_uniform("ghost_color", &field(0));
+                    shader.set_float_array_uniform("ghost_transform", &field(1));
+                    shader.set_float_array_uniform("ghost_position", &field(2));
+                    shader.set_float_array_uniform("ghost_shape", &field(3));
+
+                    quad.draw();
+                });
+            }
+
+            // render flare on top
+            main_fb.draw_with(|_fb| {
+                let shader = shader_lib.flare_variant(self.flare.style);
+                let aspect_ratio = size.0 as f32 / size.1 as f32;
+                let rotation = self.flare.rotation_at(light, aspect_ratio);
+
+                shader.bind();
+                shader.set_float_uniform("aspect_ratio", [aspect_ratio]);
+                // the other styles compile the procedural rays out
+                if self.flare.style.uses_blades() {
+                    shader.set_float_uniform("blades", [self.aperture_shape.get_blade_count() as f32]);
+                    shader.set_float_uniform("blade_curvature", [self.aperture_shape.curvature()]);
+                    // the shader adds it to the pixel angle, so the rays turn the other way
+                    shader.set_float_uniform("rotation", [-(self.aperture_shape.rotation() + rotation)]);
+                    // the ring noise turns with the flare, the ghost rotation leaves it alone
+                    shader.set_matrix_uniform("texture_rotation", *Matrix2::from_angle(Rad(rotation)).as_ref());
+                }
+                self.flare.draw(shader, light, rotation, quad);
+            });
+        }
+    }
+
+    /// Ghosts that are drawn, taking mute and solo into account.
+    pub fn visible_ghosts(&self) -> impl Iterator<Item = &Ghost> {
+        let solo = self.ghosts.iter().any(|ghost| ghost.solo);
+        self.ghosts.iter().filter(move |ghost| match solo {
+            true => ghost.solo,
+            false => !ghost.muted,
+        })
+    }
+
+    /// Aperture a ghost is drawn with, aperture images and kinds not shaped by the aperture are drawn over
+    /// the square of the circular aperture.
+    pub(crate) fn ghost_aperture(&self, ghost: &Ghost, aperture_image: bool) -> ApertureShape {
+        match aperture_image || !ghost.kind.uses_aperture() {
+            true => ApertureShape::Circular,
+            false => ghost.aperture_shape.unwrap_or(self.aperture_shape),
+        }
+    }
+
+    /// Moves the first light, adding one when there are none.
+    pub fn set_position(&mut self, position: (f32, f32)) {
+        match self.lights.first_mut() {
+            Some(light) => light.set_position(position),
+            None => self.lights.push(Light::at_position(position)),
+        }
+    }
+}
+
+impl Default for Effect {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub enum ApertureShape {
+    Polygonal(NonZeroU8),
+    Circular,
+    /// Iris of curved blades, `curvature` blends from the straight edged polygon at 0 to a circle at 1.
+    Blades {
+        count: NonZeroU8,
+        curvature: f32,
+        /// Rotation of the iris in radians, also turns the flare rays.
+        rotation: f32,
+        /// Depth of the notches where two blades meet, relative to the aperture radius.
+        #[serde(deserialize_with = "preset::required_option")]
+        notch: Option<f32>,
+    },
+}
+
+impl ApertureShape {
+    /// Blade count passed to the flare shader, circular apertures count as 255 blades.
+    pub fn get_blade_count(&self) -> u8 {
+        match self {
+            ApertureShape::Polygonal(b) => b.get(),
+            ApertureShape::Circular => 255,
+            ApertureShape::Blades { count, .. } => count.get(),
+        }
+    }
+
+    /// How round the blade edges are, 0 for straight edges and 1 for a circle.
+    pub fn curvature(&self) -> f32 {
+        match self {
+            ApertureShape::Polygonal(_) => 0.0,
+            ApertureShape::Circular => 1.0,
+            ApertureShape::Blades { curvature, .. } => *curvature,
+        }
+    }
+
+    pub fn rotation(&self) -> f32 {
+        match self {
+            ApertureShape::Blades { rotation, .. } => *rotation,
+            _ => 0.0,
+        }
+    }
+
+    pub fn from_blade_count(value: u8) -> Result<Self, LfgError> {
+        Self::try_from(value)
+    }
+
+    /// Changes the blade count, keeping the curvature, rotation and notch of curved blades.
+    pub fn with_blade_count(self, value: u8) -> Result<Self, LfgError> {
+        match (self, Self::try_from(value)?) {
+            (
+                ApertureShape::Blades {
+                    curvature, rotation, notch, ..
+                },
+                ApertureShape::Polygonal(count),
+            ) => Ok(ApertureShape::Blades {
+                count,
+                curvature,
+                rotation,
+                notch,
+            }),
+            (_, shape) => Ok(shape),
+        }
+    }
+}
+
+// ghost geometry is cached per shape, so the float fields compare and hash by their bits
+impl ApertureShape {
+    fn key(&self) -> (u8, u8, u32, u32, Option<u32>) {
+        match *self {
+            ApertureShape::Polygonal(count) => (0, count.get(), 0, 0, None),
+            ApertureShape::Circular => (1, 0, 0, 0, None),
+            ApertureShape::Blades {
+                count,
+                curvature,
+                rotation,
+                notch,
+            } => (2, count.get(), curvature.to_bits(), rotation.to_bits(), notch.map(f32::to_bits)),
+        }
+    }
+}
+
+impl PartialEq for ApertureShape {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for ApertureShape {}
+
+impl Hash for ApertureShape {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
+}
+
+impl TryFrom<u8> for ApertureShape {
+    type Error = LfgError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(ApertureShape::Circular),
+            1 | 2 => Err(LfgError::InvalidEffectValue(format!("aperture shape ({} blades)", value))),
+            _ => Ok(ApertureShape::Polygonal(NonZeroU8::new(value).unwrap())),
+        }
+    }
+}
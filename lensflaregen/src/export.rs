@@ -0,0 +1,102 @@
+//! OpenEXR output of the scene-linear HDR buffer.
+
+use std::path::Path;
+
+use exr::prelude::{f16, AnyChannel, AnyChannels, AttributeValue, Encoding, FlatSamples, Image, Layer, LayerAttributes, SmallVec, Text, Vec2, WritableImage};
+
+use crate::{preset::Preset, LfgError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExrPrecision {
+    Half,
+    Full,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExrOptions {
+    pub precision: ExrPrecision,
+    /// Adds an `A` channel holding the brightest color component clamped to `[0, 1]`,
+    /// for compositors that expect coverage. The color channels stay unpremultiplied additive light.
+    pub alpha: bool,
+}
+
+/// Full floats without alpha, like `lfg` without `--half` and `--alpha`.
+impl Default for ExrOptions {
+    fn default() -> Self {
+        Self {
+            precision: ExrPrecision::Full,
+            alpha: false,
+        }
+    }
+}
+
+/// Writes RGB triplets, with rows ordered from top to bottom as returned by
+/// [`Renderer::read_hdr`](crate::renderer::Renderer::read_hdr), into an EXR file.
+///
+/// The preset name, the position of every light and the dispersion sample count are stored as header attributes
+/// prefixed with `lensflaregen/`.
+pub fn write_exr<P: AsRef<Path>>(path: P, hdr: &[f32], size: (u32, u32), options: ExrOptions, preset: &Preset) -> Result<(), LfgError> {
+    let (width, height) = (size.0 as usize, size.1 as usize);
+    if hdr.len() != width * height * 3 {
+        return Err(LfgError::BufferSize(hdr.len(), size.0, size.1));
+    }
+
+    let channel = |name: &str, component: fn(&[f32]) -> f32| {
+        let values = hdr.chunks_exact(3).map(component);
+        let samples = match options.precision {
+            ExrPrecision::Half => FlatSamples::F16(values.map(f16::from_f32).collect()),
+            ExrPrecision::Full => FlatSamples::F32(values.collect()),
+        };
+
+        AnyChannel::new(name, samples)
+    };
+
+    let mut channels: SmallVec<[_; 4]> = SmallVec::new();
+    channels.push(channel("R", |p| p[0]));
+    channels.push(channel("G", |p| p[1]));
+    channels.push(channel("B", |p| p[2]));
+    if options.alpha {
+        channels.push(channel("A", |p| p[0].max(p[1]).max(p[2]).clamp(0.0, 1.0)));
+    }
+
+    let effect = &preset.effect;
+    let mut attributes = LayerAttributes {
+        software_name: Some(Text::from("lensflaregen")),
+        ..LayerAttributes::default()
+    };
+    attributes.other.insert(
+        Text::from("lensflaregen/preset"),
+        // OpenEXR strings are UTF-8, the `Text` constructors only accept Latin-1
+        AttributeValue::Text(Text::from_slice_unchecked(preset.name.as_bytes())),
+    );
+    for (idx, light) in effect.lights.iter().enumerate() {
+        attributes.other.insert(
+            Text::from(format!("lensflaregen/light{}/position", idx).as_str()),
+            AttributeValue::FloatVec2(Vec2(light.pos_x, light.pos_y)),
+        );
+    }
+    attributes
+        .other
+        .insert(Text::from("lensflaregen/samples"), AttributeValue::I32(effect.samples as i32));
+
+    let layer = Layer::new((width, height), attributes, Encoding::SMALL_LOSSLESS, AnyChannels::sort(channels));
+    Image::from_layer(layer).write().to_file(path)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::effect::Effect;
+
+    #[test]
+    fn mismatched_buffer_rejected() {
+        let path = std::env::temp_dir().join("lensflaregen-mismatched.exr");
+        let preset = Preset::new("mismatched", Effect::new());
+        let result = write_exr(&path, &[0.0; 3 * 4], (4, 2), ExrOptions::default(), &preset);
+
+        assert!(matches!(result, Err(LfgError::BufferSize(12, 4, 2))));
+        assert!(!path.exists());
+    }
+}
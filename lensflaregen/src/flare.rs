@@ -0,0 +1,253 @@
+use cgmath::{Matrix2, Rad};
+use gl_wrapper::{geometry::Geometry, shader::Shader};
+use serde::{Deserialize, Serialize};
+
+use crate::{light::Light, starburst::Starburst};
+
+// size of the streak direction array in `flare.frag`
+pub const MAX_STREAKS: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Flare {
+    pub color: [f32; 4],
+    pub intensity: f32,
+    pub size: f32,
+    pub ray_intensity: f32,
+    pub style: FlareStyle,
+    /// Rotation of the rays, streaks and starburst in radians, on top of the aperture rotation.
+    /// The anamorphic streak stays horizontal, like the streaks of real anamorphic lenses.
+    pub rotation: f32,
+    /// Turns the rays along with the direction from the image center to the light.
+    pub align_to_light: bool,
+    /// Horizontal streak of the anamorphic style.
+    pub anamorphic: Anamorphic,
+    /// Halo of the glow style.
+    pub glow: Glow,
+    /// Streaks of the streaks style.
+    pub streaks: Streaks,
+    /// Diffraction pattern of the starburst style, computed from the aperture.
+    pub starburst: Starburst,
+}
+
+impl Flare {
+    pub fn new() -> Self {
+        Self {
+            color: [1.0, 0.5, 0.5, 1.0],
+            intensity: 1.0,
+            ray_intensity: 1.0,
+            size: 5.0,
+            style: FlareStyle::Normal,
+            rotation: 0.0,
+            align_to_light: false,
+            anamorphic: Anamorphic::new(),
+            glow: Glow::new(),
+            streaks: Streaks::new(),
+            starburst: Starburst::new(),
+        }
+    }
+
+    /// Rotation of the flare around `light` in radians, `aspect_ratio` being the frame width over its height.
+    pub fn rotation_at(&self, light: &Light, aspect_ratio: f32) -> f32 {
+        if !self.style.uses_rotation() {
+            return 0.0;
+        }
+
+        let (x, y) = ((light.pos_x - 0.5) * aspect_ratio, light.pos_y - 0.5);
+        match self.align_to_light && (x != 0.0 || y != 0.0) {
+            true => self.rotation + y.atan2(x),
+            false => self.rotation,
+        }
+    }
+
+    /// Sets the uniforms of the flare and draws it, `rotation` being [`Flare::rotation_at`] the light.
+    pub fn draw(&self, shader: &Shader, light: &Light, rotation: f32, quad: &Geometry) {
+        shader.set_float_uniform("color", light.apply_to(self.color));
+        shader.set_float_uniform("flare_position", [light.pos_x, light.pos_y]);
+        shader.set_float_uniform("intensity", [self.intensity]);
+        shader.set_float_uniform("size", [self.size]);
+        shader.set_float_uniform("ray_intensity", [self.ray_intensity]);
+
+        match self.style {
+            FlareStyle::Anamorphic => {
+                let anamorphic = &self.anamorphic;
+                shader.set_float_uniform("anam_length", [anamorphic.length]);
+                shader.set_float_uniform("anam_thickness", [anamorphic.thickness]);
+                shader.set_float_uniform("anam_softness", [anamorphic.softness]);
+                shader.set_float_uniform("anam_falloff", [anamorphic.falloff]);
+                shader.set_float_uniform("anam_ring", [anamorphic.ring]);
+                shader.set_float_uniform("anam_core_color", anamorphic.core_color);
+                shader.set_float_uniform("anam_tip_color", anamorphic.tip_color);
+            }
+            FlareStyle::Glow => {
+                shader.set_float_uniform("glow_radius", [self.glow.radius]);
+                shader.set_float_uniform("glow_falloff", [self.glow.falloff]);
+            }
+            FlareStyle::Streaks => {
+                let directions: Vec<_> = self
+                    .streaks
+                    .angles
+                    .iter()
+                    .take(MAX_STREAKS)
+                    .map(|angle| [(angle + rotation).cos(), (angle + rotation).sin()])
+                    .collect();
+                shader.set_int_uniform("streak_count", [directions.len() as i32]);
+                if !directions.is_empty() {
+                    shader.set_float_array_uniform("streak_directions", &directions);
+                }
+                shader.set_float_uniform("streak_thickness", [self.streaks.thickness]);
+                shader.set_float_uniform("streak_length", [self.streaks.length]);
+            }
+            FlareStyle::Starburst => {
+                // turns the sampled position back, so the pattern turns forward
+                shader.set_matrix_uniform("starburst_rotation", *Matrix2::from_angle(Rad(-rotation)).as_ref());
+            }
+            FlareStyle::Normal => {}
+        }
+
+        quad.draw();
+    }
+}
+
+impl Default for Flare {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum FlareStyle {
+    Normal,
+    Anamorphic,
+    /// Soft glow without any rays.
+    Glow,
+    /// Straight streaks through the light, at the angles of [`Flare::streaks`].
+    Streaks,
+    /// Diffraction starburst of the aperture in place of the procedural rays.
+    Starburst,
+}
+
+impl FlareStyle {
+    pub const NAMES: [&'static str; 5] = ["Normal", "Anamorphic", "Glow", "Streaks", "Starburst"];
+
+    /// Style at `idx` of [`FlareStyle::NAMES`].
+    pub fn from_index(idx: usize) -> Option<Self> {
+        match idx {
+            0 => Some(FlareStyle::Normal),
+            1 => Some(FlareStyle::Anamorphic),
+            2 => Some(FlareStyle::Glow),
+            3 => Some(FlareStyle::Streaks),
+            4 => Some(FlareStyle::Starburst),
+            _ => None,
+        }
+    }
+
+    pub fn index(&self) -> usize {
+        match self {
+            FlareStyle::Normal => 0,
+            FlareStyle::Anamorphic => 1,
+            FlareStyle::Glow => 2,
+            FlareStyle::Streaks => 3,
+            FlareStyle::Starburst => 4,
+        }
+    }
+
+    /// Whether the style draws procedural rays shaped by the aperture blades.
+    pub fn uses_blades(&self) -> bool {
+        matches!(self, FlareStyle::Normal | FlareStyle::Anamorphic)
+    }
+
+    /// Whether [`Flare::rotation`] turns the style, glows are round and anamorphic streaks stay horizontal.
+    pub fn uses_rotation(&self) -> bool {
+        matches!(self, FlareStyle::Normal | FlareStyle::Streaks | FlareStyle::Starburst)
+    }
+}
+
+/// Horizontal streak through the light, as drawn by anamorphic lenses.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Anamorphic {
+    /// Width of the gaussian the streak fades out with horizontally, relative to the frame height.
+    pub length: f32,
+    /// Half of the height of the streak at its core, relative to the frame height.
+    pub thickness: f32,
+    /// Width of the soft streak edge, relative to the frame height.
+    pub softness: f32,
+    /// How quickly the streak narrows towards its tips.
+    pub falloff: f32,
+    /// Strength of the noisy ring around the core.
+    pub ring: f32,
+    /// Tint of the streak at the light, blended into `tip_color` over the streak length.
+    pub core_color: [f32; 3],
+    pub tip_color: [f32; 3],
+}
+
+impl Anamorphic {
+    pub fn new() -> Self {
+        Self {
+            length: 0.5,
+            thickness: 0.01,
+            softness: 0.0125,
+            falloff: 0.4,
+            ring: 0.2,
+            core_color: [1.0, 1.0, 1.0],
+            tip_color: [1.0, 1.0, 1.0],
+        }
+    }
+}
+
+impl Default for Anamorphic {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Halo around the flare, fading out towards `radius`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Glow {
+    /// Radius of the halo, relative to the frame height.
+    pub radius: f32,
+    /// Exponent of the fade, higher values keep the halo closer to the light.
+    pub falloff: f32,
+}
+
+impl Glow {
+    pub fn new() -> Self {
+        Self { radius: 0.4, falloff: 3.0 }
+    }
+}
+
+impl Default for Glow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Straight streaks through the light, each one reaching out to both sides.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Streaks {
+    /// Angle of every streak in radians, counterclockwise from the horizontal. At most [`MAX_STREAKS`] are drawn.
+    pub angles: Vec<f32>,
+    /// Width of the streaks, relative to the frame height.
+    pub thickness: f32,
+    /// Distance from the light at which the streaks fade out, relative to the frame height.
+    pub length: f32,
+}
+
+impl Streaks {
+    pub fn new() -> Self {
+        Self {
+            angles: vec![0.0, std::f32::consts::FRAC_PI_3, 2.0 * std::f32::consts::FRAC_PI_3],
+            thickness: 0.003,
+            length: 0.8,
+        }
+    }
+}
+
+impl Default for Streaks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,294 @@
+use std::f32::consts::PI;
+
+use cgmath::{prelude::*, vec2, Deg, Matrix2, Rad, Vector2};
+use serde::{Deserialize, Serialize};
+
+use crate::{animation::Track, effect::ApertureShape, light::Light, preset};
+
+use gl_wrapper::geometry::{AttrSize, Geometry, GeometryBuilder, GeometryType};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Ghost {
+    /// Label shown in the UI, empty for unnamed ghosts.
+    pub name: String,
+    pub color: [f32; 4],
+    pub offset: f32,
+    pub perpendicular_offset: f32,
+    pub size: f32,
+    pub dispersion: f32,
+    pub distortion: f32,
+    pub intensity: f32,
+    pub center_transparency: f32,
+    pub aspect_ratio: f32,
+    pub dispersion_center: DispersionCenter,
+    pub kind: GhostKind,
+    /// Overrides the aperture of the effect for this ghost.
+    #[serde(deserialize_with = "preset::required_option")]
+    pub aperture_shape: Option<ApertureShape>,
+    pub response: GhostResponse,
+    /// Skipped when drawing.
+    pub muted: bool,
+    /// Once any ghost is soloed, only soloed ghosts are drawn, muted or not.
+    pub solo: bool,
+}
+
+impl Ghost {
+    pub fn new() -> Self {
+        Ghost {
+            name: String::new(),
+            color: [0.5, 0.5, 0.5, 1.0],
+            offset: -1.0,
+            perpendicular_offset: 0.0,
+            size: 30.0,
+            intensity: 2.0,
+            dispersion: 0.1,
+            distortion: 0.9,
+            center_transparency: 1.0,
+            aspect_ratio: 1.0,
+            dispersion_center: DispersionCenter::Image,
+            kind: GhostKind::Aperture,
+            aperture_shape: None,
+            response: GhostResponse::default(),
+            muted: false,
+            solo: false,
+        }
+    }
+
+    /// Uniforms of the ghost in `ghosts.frag`, `None` when it covers no area.
+    ///
+    /// `shape_tile` is the tile of its aperture in the shape atlas, negative for circular apertures.
+    pub(crate) fn pass_uniforms(&self, light: &Light, effect_rotation: f32, (width, height): (u32, u32), shape_tile: f32) -> Option<[[f32; 4]; 4]> {
+        let flare_pos = light.position();
+        let (intensity, size) = self.response.multipliers(flare_pos);
+        let scale = self.size * size / 100.0;
+        if scale <= 0.0 || self.aspect_ratio <= 0.0 {
+            return None;
+        }
+
+        let [r, g, b, _] = light.apply_to(self.color);
+        let intensity = self.intensity * intensity;
+        let color = [r * intensity, g * intensity, b * intensity, self.dispersion];
+
+        // inverse of placing the aperture, which is turned by the negated angle and stretched by the image aspect
+        let aspect_ratio = width as f32 / height as f32;
+        let angle = self.rotation(effect_rotation, flare_pos, (width, height));
+        let to_ghost = Matrix2::from_angle(Rad(angle)) * Matrix2::new(self.aspect_ratio / scale, 0.0, 0.0, 1.0 / (aspect_ratio * scale));
+        let transform: &[f32; 4] = to_ghost.as_ref();
+
+        let ghost_pos = self.ghost_pos_from_flare_pos(flare_pos);
+        let from_ghost_center = match self.dispersion_center {
+            DispersionCenter::Ghost => 1.0,
+            DispersionCenter::Image => 0.0,
+        };
+        let position = [ghost_pos.x, ghost_pos.y, self.distortion, from_ghost_center];
+
+        let (param_a, param_b) = match self.kind {
+            GhostKind::Aperture => (self.center_transparency, 0.0),
+            GhostKind::Disc { softness } => (softness, 0.0),
+            GhostKind::Ring { width } => (width, 0.0),
+            GhostKind::Streak { thickness, falloff } => (thickness, falloff),
+            GhostKind::Halo { width } => (width, 0.0),
+        };
+        let shape = [self.kind.index() as f32, shape_tile, param_a, param_b];
+
+        Some([color, *transform, position, shape])
+    }
+
+    /// Angle the ghost geometry is turned by, streaks follow the axis from the image center to the light.
+    pub(crate) fn rotation(&self, effect_rotation: f32, (pos_x, pos_y): (f32, f32), (width, height): (u32, u32)) -> f32 {
+        match self.kind {
+            // the geometry is turned by the negated angle, like `Effect::rotation`
+            GhostKind::Streak { .. } => -((pos_y - 0.5) * height as f32).atan2((pos_x - 0.5) * width as f32),
+            _ => effect_rotation,
+        }
+    }
+
+    pub(crate) fn ghost_pos_from_flare_pos(&self, flare_pos: (f32, f32)) -> Vector2<f32> {
+        let flare_vec = Vector2::from(flare_pos);
+
+        // map from <0.0; 1.0> to <-1.0; 1.0>
+        let mut ghost_pos = (flare_vec * 2.0 - Vector2::from_value(1.0)) * self.offset;
+
+        // mapped to direction vector from image center
+        let flare_vec_mapped = (flare_vec - vec2(0.5, 0.5)).normalize();
+
+        // add perpendicular offset
+        ghost_pos.x += flare_vec_mapped.y * self.perpendicular_offset;
+        ghost_pos.y += -flare_vec_mapped.x * self.perpendicular_offset;
+
+        ghost_pos
+    }
+}
+
+impl Default for Ghost {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub fn gen_ghost_geo(shape: ApertureShape) -> Geometry {
+    GeometryBuilder::new(ghost_geo_vertices(shape))
+        .mode(GeometryType::TriangleFan)
+        .with_attributes(&[AttrSize::Vec2, AttrSize::Float])
+        .build()
+}
+
+/// Triangle fan of the aperture polygon, as `x, y, edge` triplets.
+///
+/// Circular apertures get a square around the unit disc, the fragment shader cuts out the disc itself.
+pub(crate) fn ghost_geo_vertices(shape: ApertureShape) -> Vec<f32> {
+    let blades = match shape {
+        ApertureShape::Polygonal(blades) => blades.get() as u32,
+        ApertureShape::Circular => return vec![0.0, 0.0, 0.0, 1.0, -1.0, 0.0, 1.0, 1.0, 0.0, -1.0, 1.0, 0.0, -1.0, -1.0, 0.0, 1.0, -1.0, 0.0],
+        ApertureShape::Blades {
+            count,
+            curvature,
+            rotation,
+            notch,
+        } => return blade_vertices(count.get() as u32, curvature, rotation, notch.unwrap_or(0.0)),
+    };
+
+    let mut vert_data = Vec::with_capacity((blades as usize + 2) * 3);
+    vert_data.extend_from_slice(&[0.0, 0.0, 0.0]);
+
+    let mut start = cgmath::vec2(1.0, 0.0);
+
+    for _ in 0..=blades {
+        vert_data.extend_from_slice(&[start.x, start.y, 1.0]);
+        start = Matrix2::from_angle(Deg(360.0 / blades as f32)) * start;
+    }
+
+    vert_data
+}
+
+// rim of curved blades, every edge split into segments pushed out towards the circle
+fn blade_vertices(blades: u32, curvature: f32, rotation: f32, notch: f32) -> Vec<f32> {
+    const SEGMENTS: u32 = 16;
+    // width of a notch, as a fraction of the blade edge
+    const NOTCH_WIDTH: f32 = 0.15;
+
+    let edge_angle = 2.0 * PI / blades as f32;
+    let mut vert_data = Vec::with_capacity(((blades * SEGMENTS) as usize + 2) * 3);
+    vert_data.extend_from_slice(&[0.0, 0.0, 0.0]);
+
+    for idx in 0..=blades * SEGMENTS {
+        let along_edge = (idx % SEGMENTS) as f32 / SEGMENTS as f32;
+        let from_center = (along_edge - 0.5) * edge_angle;
+
+        let polygon_radius = (edge_angle / 2.0).cos() / from_center.cos();
+        let mut radius = polygon_radius * (1.0 - curvature) + curvature;
+
+        let from_corner = along_edge.min(1.0 - along_edge) / NOTCH_WIDTH;
+        radius *= 1.0 - notch * (1.0 - from_corner).max(0.0).powi(2);
+
+        let angle = rotation + idx as f32 / SEGMENTS as f32 * edge_angle;
+        vert_data.extend_from_slice(&[radius * angle.cos(), radius * angle.sin(), 1.0]);
+    }
+
+    vert_data
+}
+
+/// Multipliers of a ghost depending on where the light is, curves without keys leave the ghost unchanged.
+///
+/// The key times of the distance curves are the distance of the light from the image center, 1 at the middle of
+/// the frame edges. The angle curves are keyed by the angle of the light around the image center in radians,
+/// from -π to π, counterclockwise from the right.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GhostResponse {
+    pub intensity: Track<f32>,
+    pub size: Track<f32>,
+    pub intensity_angle: Track<f32>,
+    pub size_angle: Track<f32>,
+}
+
+impl GhostResponse {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Distance and angle of a light at `flare_pos`, as the curves are keyed.
+    pub fn light_polar((pos_x, pos_y): (f32, f32)) -> (f32, f32) {
+        let (x, y) = (pos_x * 2.0 - 1.0, pos_y * 2.0 - 1.0);
+        ((x * x + y * y).sqrt(), y.atan2(x))
+    }
+
+    /// Intensity and size multipliers for a light at `flare_pos`.
+    pub fn multipliers(&self, flare_pos: (f32, f32)) -> (f32, f32) {
+        let (distance, angle) = Self::light_polar(flare_pos);
+        let sample = |track: &Track<f32>, x: f32| track.sample(x).unwrap_or(1.0);
+
+        (
+            sample(&self.intensity, distance) * sample(&self.intensity_angle, angle),
+            sample(&self.size, distance) * sample(&self.size_angle, angle),
+        )
+    }
+}
+
+/// Profile drawn inside a ghost, every kind goes through the same dispersion pass.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub enum GhostKind {
+    /// Aperture shape with a bright rim, its center faded out by `Ghost::center_transparency`.
+    #[default]
+    Aperture,
+    /// Evenly filled aperture shape, `softness` fades the edge in over that fraction of the radius.
+    Disc { softness: f32 },
+    /// Outline of the aperture shape, `width` relative to the ghost radius.
+    Ring { width: f32 },
+    /// Line along the axis from the image center to the light, `thickness` relative to its half length,
+    /// `falloff` shapes the fade towards both ends.
+    Streak { thickness: f32, falloff: f32 },
+    /// Soft circular ring, ignoring the aperture, `width` relative to the ghost radius.
+    Halo { width: f32 },
+}
+
+impl GhostKind {
+    pub const NAMES: [&'static str; 5] = ["Aperture", "Disc", "Ring", "Streak", "Halo"];
+
+    /// Kind at `idx` of [`GhostKind::NAMES`], with default parameters.
+    pub fn from_index(idx: usize) -> Option<Self> {
+        match idx {
+            0 => Some(GhostKind::Aperture),
+            1 => Some(GhostKind::Disc { softness: 0.1 }),
+            2 => Some(GhostKind::Ring { width: 0.1 }),
+            3 => Some(GhostKind::Streak { thickness: 0.05, falloff: 1.0 }),
+            4 => Some(GhostKind::Halo { width: 0.1 }),
+            _ => None,
+        }
+    }
+
+    pub fn index(&self) -> usize {
+        match self {
+            GhostKind::Aperture => 0,
+            GhostKind::Disc { .. } => 1,
+            GhostKind::Ring { .. } => 2,
+            GhostKind::Streak { .. } => 3,
+            GhostKind::Halo { .. } => 4,
+        }
+    }
+
+    /// Whether the kind is shaped by the aperture, the others are drawn over the square around the unit circle.
+    pub fn uses_aperture(&self) -> bool {
+        !matches!(self, GhostKind::Streak { .. } | GhostKind::Halo { .. })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DispersionCenter {
+    Ghost,
+    Image,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kind_rejects_unknown_fields() {
+        assert_eq!(ron::from_str::<GhostKind>("Disc(softness: 0.1)").unwrap(), GhostKind::Disc { softness: 0.1 });
+        assert!(ron::from_str::<GhostKind>("Disc(softness: 0.1, bogus: 3.0)").is_err());
+        assert!(ron::from_str::<GhostKind>("Streak(thickness: 0.1, falloff: 1.0, bogus: 3.0)").is_err());
+    }
+}
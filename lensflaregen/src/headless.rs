@@ -0,0 +1,87 @@
+use std::ptr;
+
+use khronos_egl as egl;
+
+use crate::{effect::Effect, renderer::Renderer, LfgError};
+
+// from EGL_MESA_platform_surfaceless
+const PLATFORM_SURFACELESS_MESA: egl::Enum = 0x31DD;
+
+/// OpenGL 4.5 core context without any surface, window or event loop.
+pub struct HeadlessContext {
+    display: egl::Display,
+    context: egl::Context,
+}
+
+impl HeadlessContext {
+    pub fn new() -> Result<Self, LfgError> {
+        let display = egl::API
+            .get_platform_display(PLATFORM_SURFACELESS_MESA, egl::DEFAULT_DISPLAY, &[egl::ATTRIB_NONE])
+            .map_err(|e| LfgError::HeadlessContext(format!("surfaceless EGL platform is not available ({})", e)))?;
+        egl::API.initialize(display)?;
+        egl::API.bind_api(egl::OPENGL_API)?;
+
+        // surfaceless configs only advertise pbuffer support
+        let config_attributes = [egl::SURFACE_TYPE, egl::PBUFFER_BIT, egl::RENDERABLE_TYPE, egl::OPENGL_BIT, egl::NONE];
+        let config = egl::API
+            .choose_first_config(display, &config_attributes)?
+            .ok_or_else(|| LfgError::HeadlessContext("no suitable EGL config found".into()))?;
+
+        let context_attributes = [
+            egl::CONTEXT_MAJOR_VERSION,
+            4,
+            egl::CONTEXT_MINOR_VERSION,
+            5,
+            egl::CONTEXT_OPENGL_PROFILE_MASK,
+            egl::CONTEXT_OPENGL_CORE_PROFILE_BIT,
+            egl::NONE,
+        ];
+        let context = egl::API.create_context(display, config, None, &context_attributes)?;
+        egl::API.make_current(display, None, None, Some(context))?;
+
+        gl::load_with(|s| egl::API.get_proc_address(s).map_or(ptr::null(), |f| f as *const _));
+
+        Ok(Self { display, context })
+    }
+}
+
+impl Drop for HeadlessContext {
+    fn drop(&mut self) {
+        egl::API.make_current(self.display, None, None, None).ok();
+        egl::API.destroy_context(self.display, self.context).ok();
+        egl::API.terminate(self.display).ok();
+    }
+}
+
+/// Renders effects into memory, without any window, event loop or UI.
+pub struct HeadlessRenderer {
+    renderer: Renderer,
+    // declared last, so all GL objects in the renderer are deleted while the context is still alive
+    _context: HeadlessContext,
+}
+
+impl HeadlessRenderer {
+    pub fn with_size(width: u32, height: u32) -> Result<Self, LfgError> {
+        let context = HeadlessContext::new()?;
+        let renderer = Renderer::new(width, height)?;
+
+        Ok(Self { renderer, _context: context })
+    }
+
+    /// Renders `effect` with its first light moved to `flare_pos` and returns the linear HDR buffer.
+    ///
+    /// The buffer holds `width * height` RGB triplets, with rows ordered from top to bottom.
+    pub fn render(&mut self, effect: &Effect, flare_pos: (f32, f32), (width, height): (u32, u32)) -> Vec<f32> {
+        let mut effect = effect.clone();
+        effect.set_position(flare_pos);
+
+        self.renderer.resize(width, height);
+        self.renderer.render(&effect);
+        self.renderer.read_hdr()
+    }
+
+    /// Gives access to the underlying renderer, for callers that need more than a single HDR readback.
+    pub fn renderer_mut(&mut self) -> &mut Renderer {
+        &mut self.renderer
+    }
+}
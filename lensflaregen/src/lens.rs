@@ -0,0 +1,396 @@
+//! Ghosts derived from a lens prescription with paraxial ray transfer matrices.
+
+use std::{fs, path::Path};
+
+use cgmath::{Matrix2, SquareMatrix};
+use serde::{Deserialize, Serialize};
+
+use crate::{effect::Effect, ghost::Ghost, LfgError};
+
+// smaller ghosts are drawn at this size
+const MIN_GHOST_SIZE: f32 = 1.0;
+
+// ghosts focused much tighter than this barely get any brighter, the dispersion pass blurs them out anyway
+const BRIGHTNESS_SIZE_FLOOR: f32 = 10.0;
+
+// ghosts further from the center never get into the frame
+const MAX_GHOST_OFFSET: f32 = 5.0;
+
+/// One refracting surface, distances in millimeters.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Surface {
+    /// Radius of curvature, positive when the center lies behind the surface, 0 for a flat surface.
+    pub radius: f32,
+    /// Distance to the next surface, ignored for the last one.
+    pub thickness: f32,
+    /// Refractive index of the medium behind the surface.
+    pub ior: f32,
+    /// Fraction of light reflected by the coated surface.
+    pub reflectance: f32,
+}
+
+/// Lens prescription, surfaces ordered from the front element to the back.
+///
+/// The sensor is placed at the paraxial focus behind the last surface.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Prescription {
+    pub name: String,
+    pub surfaces: Vec<Surface>,
+    pub f_number: f32,
+    /// Height of the sensor in millimeters, the frame height.
+    pub sensor_height: f32,
+}
+
+impl Prescription {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, LfgError> {
+        let prescription: Self = ron::from_str(&fs::read_to_string(path)?).map_err(LfgError::LensParse)?;
+        prescription.validate()?;
+
+        Ok(prescription)
+    }
+
+    /// Prescriptions shipped with the library.
+    pub fn builtin() -> Vec<Self> {
+        vec![Self::double_gauss(), Self::zoom_wide(), Self::zoom_tele()]
+    }
+
+    /// 50 mm f/2 double Gauss, US patent 2,673,491 scaled from 100 mm.
+    pub fn double_gauss() -> Self {
+        Self {
+            name: "Double Gauss 50mm f/2".into(),
+            surfaces: surfaces(&[
+                (29.475, 3.76, 1.67, COATED),
+                (84.83, 0.12, 1.0, COATED),
+                (19.275, 4.025, 1.67, COATED),
+                (40.77, 3.275, 1.699, CEMENTED),
+                (12.75, 5.705, 1.0, COATED),
+                // aperture stop
+                (0.0, 4.5, 1.0, 0.0),
+                (-14.495, 1.18, 1.603, COATED),
+                (40.77, 6.065, 1.658, CEMENTED),
+                (-20.385, 0.19, 1.0, COATED),
+                (437.065, 3.22, 1.717, COATED),
+                (-39.73, 0.0, 1.0, COATED),
+            ]),
+            f_number: 2.0,
+            sensor_height: 24.0,
+        }
+    }
+
+    /// Four group 14-42 mm zoom for a Four Thirds sensor, at its wide end.
+    pub fn zoom_wide() -> Self {
+        Self::zoom("Zoom 14-42mm at 14mm", 0.5, 29.5)
+    }
+
+    /// Four group 14-42 mm zoom for a Four Thirds sensor, at its tele end.
+    pub fn zoom_tele() -> Self {
+        Self::zoom("Zoom 14-42mm at 42mm", 23.7, 6.3)
+    }
+
+    // positive focusing group, negative variator, positive compensator and a fixed prime group,
+    // only the gaps around the variator change with the focal length
+    fn zoom(name: &str, front_gap: f32, variator_gap: f32) -> Self {
+        Self {
+            name: name.into(),
+            surfaces: surfaces(&[
+                (120.0, 2.0, 1.805, COATED),
+                (48.0, 8.0, 1.62, CEMENTED),
+                (-320.0, 0.2, 1.0, COATED),
+                (42.0, 5.5, 1.697, COATED),
+                (110.0, front_gap, 1.0, COATED),
+                (70.0, 1.2, 1.773, COATED),
+                (14.5, 5.0, 1.0, COATED),
+                (-40.0, 1.2, 1.697, COATED),
+                (22.0, 3.5, 1.847, CEMENTED),
+                (-400.0, variator_gap, 1.0, COATED),
+                (60.0, 3.5, 1.697, COATED),
+                (-60.0, 2.0, 1.0, COATED),
+                // aperture stop
+                (0.0, 2.0, 1.0, 0.0),
+                (24.0, 5.0, 1.62, COATED),
+                (-30.0, 1.2, 1.805, CEMENTED),
+                (90.0, 6.0, 1.0, COATED),
+                (40.0, 4.0, 1.589, COATED),
+                (-75.0, 0.0, 1.0, COATED),
+            ]),
+            f_number: 4.0,
+            sensor_height: 13.0,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), LfgError> {
+        if self.surfaces.is_empty() {
+            return Err(invalid("surfaces", "at least one surface is required"));
+        }
+        if !(self.f_number.is_finite() && self.f_number > 0.0) {
+            return Err(invalid("f_number", "must be positive"));
+        }
+        if !(self.sensor_height.is_finite() && self.sensor_height > 0.0) {
+            return Err(invalid("sensor_height", "must be positive"));
+        }
+
+        for (idx, surface) in self.surfaces.iter().enumerate() {
+            let name = |field: &str| format!("surfaces[{}].{}", idx, field);
+            if !surface.radius.is_finite() {
+                return Err(invalid(&name("radius"), "must be finite"));
+            }
+            if !(surface.thickness.is_finite() && surface.thickness >= 0.0) {
+                return Err(invalid(&name("thickness"), "must not be negative"));
+            }
+            if !(surface.ior.is_finite() && surface.ior > 0.0) {
+                return Err(invalid(&name("ior"), "must be positive"));
+            }
+            if !(0.0..=1.0).contains(&surface.reflectance) {
+                return Err(invalid(&name("reflectance"), "must be between 0 and 1"));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Effective focal length in millimeters.
+    pub fn focal_length(&self) -> Result<f32, LfgError> {
+        let (system, sensor_distance) = self.primary()?;
+        Ok((translation(sensor_distance) * system).y.x)
+    }
+
+    /// Ghosts of every path reflected by two surfaces, at most `max_ghosts` of the brightest ones.
+    ///
+    /// Offsets and sizes follow from the ray transfer matrices, intensities are scaled so the brightest ghost
+    /// gets the default ghost intensity.
+    pub fn ghosts(&self, max_ghosts: usize) -> Result<Vec<Ghost>, LfgError> {
+        self.validate()?;
+
+        let (primary, sensor_distance) = self.primary()?;
+        let to_sensor = translation(sensor_distance);
+        let focal_length = (to_sensor * primary).y.x;
+
+        let entrance_radius = focal_length.abs() / (2.0 * self.f_number);
+        let sensor_radius = self.sensor_height / 2.0;
+
+        let mut ghosts = Vec::new();
+        for second in 1..self.surfaces.len() {
+            for first in 0..second {
+                let reflectance = self.surfaces[first].reflectance * self.surfaces[second].reflectance;
+                if reflectance <= 0.0 {
+                    continue;
+                }
+
+                let system = to_sensor * self.ghost_path(first, second);
+
+                // the chief ray sets the position, the marginal rays the size of the defocused aperture image
+                let offset = system.y.x / focal_length;
+                let image_size = 100.0 * system.x.x.abs() * entrance_radius / sensor_radius;
+                let size = image_size.max(MIN_GHOST_SIZE);
+                if !offset.is_finite() || offset.abs() > MAX_GHOST_OFFSET {
+                    continue;
+                }
+
+                // the reflected light is spread over the area of the ghost, the floor is added in quadrature
+                // so smaller ghosts stay brighter
+                let brightness = reflectance / (image_size * image_size + BRIGHTNESS_SIZE_FLOOR * BRIGHTNESS_SIZE_FLOOR);
+                ghosts.push((brightness, Ghost { offset, size, ..Ghost::new() }));
+            }
+        }
+
+        ghosts.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+        ghosts.truncate(max_ghosts);
+
+        let max_brightness = ghosts.first().map_or(1.0, |(brightness, _)| *brightness);
+        let intensity = Ghost::new().intensity;
+
+        Ok(ghosts
+            .into_iter()
+            .map(|(brightness, ghost)| Ghost {
+                intensity: intensity * brightness / max_brightness,
+                ..ghost
+            })
+            .collect())
+    }
+
+    /// Replaces the ghosts of `effect` with the ones of this lens.
+    pub fn apply(&self, effect: &mut Effect, max_ghosts: usize) -> Result<(), LfgError> {
+        effect.ghosts = self.ghosts(max_ghosts)?;
+        Ok(())
+    }
+
+    // matrix of the unreflected path through the last surface, and the distance of its focus
+    fn primary(&self) -> Result<(Matrix2<f32>, f32), LfgError> {
+        let mut system = Matrix2::identity();
+        for idx in 0..self.surfaces.len() {
+            system = self.refraction(idx, Direction::Forward) * system;
+            if idx + 1 < self.surfaces.len() {
+                system = translation(self.surfaces[idx].thickness) * system;
+            }
+        }
+
+        // focus where the height no longer depends on the height at the entrance
+        let sensor_distance = -system.x.x / system.x.y;
+        if !sensor_distance.is_finite() || sensor_distance <= 0.0 {
+            return Err(invalid("surfaces", "the lens does not form a real image"));
+        }
+
+        Ok((system, sensor_distance))
+    }
+
+    // into the lens up to `second`, back to `first`, and out through the last surface
+    fn ghost_path(&self, first: usize, second: usize) -> Matrix2<f32> {
+        let last = self.surfaces.len() - 1;
+        let mut system = Matrix2::identity();
+
+        for idx in 0..second {
+            system = translation(self.surfaces[idx].thickness) * self.refraction(idx, Direction::Forward) * system;
+        }
+        system = reflection(self.surfaces[second].radius) * system;
+
+        for idx in (first + 1..second).rev() {
+            system = self.refraction(idx, Direction::Backward) * translation(self.surfaces[idx].thickness) * system;
+        }
+        system = reflection(-self.surfaces[first].radius) * translation(self.surfaces[first].thickness) * system;
+
+        for idx in first + 1..=last {
+            system = self.refraction(idx, Direction::Forward) * translation(self.surfaces[idx - 1].thickness) * system;
+        }
+
+        system
+    }
+
+    fn refraction(&self, idx: usize, direction: Direction) -> Matrix2<f32> {
+        let before = match idx {
+            0 => 1.0,
+            _ => self.surfaces[idx - 1].ior,
+        };
+        let after = self.surfaces[idx].ior;
+        let curvature = curvature(self.surfaces[idx].radius);
+
+        // going backward the surface is seen mirrored, from the other medium
+        let (n1, n2, curvature) = match direction {
+            Direction::Forward => (before, after, curvature),
+            Direction::Backward => (after, before, -curvature),
+        };
+
+        matrix(1.0, 0.0, (n1 - n2) * curvature / n2, n1 / n2)
+    }
+}
+
+impl Default for Prescription {
+    fn default() -> Self {
+        Self::double_gauss()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Forward,
+    Backward,
+}
+
+// single layer MgF2 coating, and the barely reflecting glass to glass interface of cemented elements
+const COATED: f32 = 0.015;
+const CEMENTED: f32 = 0.001;
+
+fn surfaces(data: &[(f32, f32, f32, f32)]) -> Vec<Surface> {
+    data.iter()
+        .map(|&(radius, thickness, ior, reflectance)| Surface {
+            radius,
+            thickness,
+            ior,
+            reflectance,
+        })
+        .collect()
+}
+
+fn curvature(radius: f32) -> f32 {
+    match radius == 0.0 {
+        true => 0.0,
+        false => 1.0 / radius,
+    }
+}
+
+// mirror hit from the front, acting on the unfolded path
+fn reflection(radius: f32) -> Matrix2<f32> {
+    matrix(1.0, 0.0, 2.0 * curvature(radius), 1.0)
+}
+
+fn translation(distance: f32) -> Matrix2<f32> {
+    matrix(1.0, distance, 0.0, 1.0)
+}
+
+// row major `[[a, b], [c, d]]` acting on (height, angle) columns
+fn matrix(a: f32, b: f32, c: f32, d: f32) -> Matrix2<f32> {
+    Matrix2::new(a, c, b, d)
+}
+
+fn invalid(name: &str, reason: &str) -> LfgError {
+    LfgError::LensValue(name.into(), reason.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn thin_lens(radius: f32, ior: f32) -> Prescription {
+        Prescription {
+            name: "thin".into(),
+            surfaces: surfaces(&[(radius, 0.0, ior, COATED), (-radius, 0.0, 1.0, COATED)]),
+            f_number: 2.0,
+            sensor_height: 24.0,
+        }
+    }
+
+    #[test]
+    fn thin_lens_focal_length() {
+        // lensmaker's equation, 1 / f = (n - 1) * (1 / R1 - 1 / R2)
+        let focal_length = thin_lens(100.0, 1.5).focal_length().unwrap();
+        assert!((focal_length - 100.0).abs() < 0.01, "{}", focal_length);
+    }
+
+    #[test]
+    fn builtin_focal_lengths() {
+        let expected = [50.0, 14.0, 42.0];
+        for (prescription, expected) in Prescription::builtin().iter().zip(&expected) {
+            prescription.validate().unwrap();
+            let focal_length = prescription.focal_length().unwrap();
+            assert!((focal_length - expected).abs() < 1.0, "{} has {} mm", prescription.name, focal_length);
+        }
+    }
+
+    #[test]
+    fn ghosts_keep_relative_intensities() {
+        for prescription in Prescription::builtin() {
+            let ghosts = prescription.ghosts(12).unwrap();
+            assert_eq!(ghosts.len(), 12);
+            assert_eq!(ghosts[0].intensity, Ghost::new().intensity);
+            assert!(ghosts.windows(2).all(|pair| pair[0].intensity >= pair[1].intensity), "{}", prescription.name);
+            assert!(ghosts.last().unwrap().intensity < ghosts[0].intensity, "{}", prescription.name);
+        }
+    }
+
+    #[test]
+    fn invalid_prescriptions_rejected() {
+        let no_surfaces = Prescription {
+            surfaces: Vec::new(),
+            ..Prescription::double_gauss()
+        };
+        assert!(matches!(no_surfaces.ghosts(8), Err(LfgError::LensValue(name, _)) if name == "surfaces"));
+
+        let mut negative_thickness = Prescription::double_gauss();
+        negative_thickness.surfaces[2].thickness = -1.0;
+        assert!(matches!(negative_thickness.validate(), Err(LfgError::LensValue(name, _)) if name == "surfaces[2].thickness"));
+
+        // a diverging lens has no real focus
+        assert!(matches!(thin_lens(-100.0, 1.5).ghosts(8), Err(LfgError::LensValue(name, _)) if name == "surfaces"));
+    }
+
+    #[test]
+    fn unparsable_file_rejected() {
+        let path = std::env::temp_dir().join("lensflaregen-unparsable-lens.ron");
+        fs::write(&path, "(name: \"broken\", surfaces: [").unwrap();
+        let result = Prescription::load(&path);
+        fs::remove_file(&path).ok();
+
+        assert!(matches!(result, Err(LfgError::LensParse(_))));
+    }
+}
@@ -0,0 +1,54 @@
+use gl_wrapper::shader::ShaderCompilationError;
+use thiserror::Error;
+
+pub mod animation;
+pub mod aperture;
+pub mod background;
+pub mod cpu;
+pub mod effect;
+pub mod export;
+pub mod flare;
+pub mod generator;
+pub mod ghost;
+pub mod headless;
+pub mod lens;
+pub mod light;
+pub mod occlusion;
+pub mod preset;
+pub mod renderer;
+mod sampling;
+pub mod shader_lib;
+pub mod spectrum;
+pub mod starburst;
+
+#[derive(Error, Debug)]
+pub enum LfgError {
+    #[error("Invalid value for parameter {0}")]
+    InvalidEffectValue(String),
+    #[error("Shader compilation error: {0}")]
+    ShaderCompilation(#[from] ShaderCompilationError),
+    #[error("Image error: {0}")]
+    Image(#[from] image::ImageError),
+    #[error("Headless context error: {0}")]
+    HeadlessContext(String),
+    #[error("EGL error: {0}")]
+    Egl(#[from] khronos_egl::Error),
+    #[error("EXR error: {0}")]
+    Exr(#[from] exr::error::Error),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Preset parse error: {0}")]
+    PresetParse(#[from] ron::error::SpannedError),
+    #[error("Preset serialization error: {0}")]
+    PresetSerialize(#[from] ron::Error),
+    #[error("Unsupported preset version {0}, expected {1}")]
+    PresetVersion(u32, u32),
+    #[error("Invalid value for parameter {0}: {1}")]
+    PresetValue(String, String),
+    #[error("Lens prescription parse error: {0}")]
+    LensParse(ron::error::SpannedError),
+    #[error("Invalid lens prescription value {0}: {1}")]
+    LensValue(String, String),
+    #[error("HDR buffer of {0} values doesn't match a {1}x{2} image")]
+    BufferSize(usize, u32, u32),
+}
@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+
+/// A light source in the frame, each one draws the flare and the whole ghost chain of an [`crate::effect::Effect`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Light {
+    pub pos_x: f32,
+    pub pos_y: f32,
+    pub intensity: f32,
+    pub tint: [f32; 3],
+}
+
+impl Light {
+    pub fn new() -> Self {
+        Self {
+            pos_x: 0.8,
+            pos_y: 0.8,
+            intensity: 1.0,
+            tint: [1.0, 1.0, 1.0],
+        }
+    }
+
+    pub fn at_position((pos_x, pos_y): (f32, f32)) -> Self {
+        Self { pos_x, pos_y, ..Self::new() }
+    }
+
+    pub fn position(&self) -> (f32, f32) {
+        (self.pos_x, self.pos_y)
+    }
+
+    pub fn set_position(&mut self, (pos_x, pos_y): (f32, f32)) {
+        self.pos_x = pos_x;
+        self.pos_y = pos_y;
+    }
+
+    /// Multiplies a flare or ghost color by the tint and intensity of this light.
+    pub fn apply_to(&self, color: [f32; 4]) -> [f32; 4] {
+        let [r, g, b] = self.tint;
+        [
+            color[0] * r * self.intensity,
+            color[1] * g * self.intensity,
+            color[2] * b * self.intensity,
+            color[3],
+        ]
+    }
+}
+
+impl Default for Light {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,129 @@
+//! Light visibility from occlusion mattes, so flares fade when something passes in front of the light.
+
+use std::{f32::consts::PI, path::Path, str::FromStr};
+
+use crate::{
+    background,
+    effect::Effect,
+    sampling::{self, Edge},
+    LfgError,
+};
+
+pub const DEFAULT_RADIUS: f32 = 0.02;
+
+// points of the golden angle spiral covering the light disc
+const DISC_SAMPLES: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaskChannel {
+    Alpha,
+    Luminance,
+}
+
+impl FromStr for MaskChannel {
+    type Err = LfgError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "alpha" => Ok(MaskChannel::Alpha),
+            "luminance" => Ok(MaskChannel::Luminance),
+            _ => Err(LfgError::InvalidEffectValue("mask channel".into())),
+        }
+    }
+}
+
+/// Per pixel light visibility, 1 where the light passes and 0 where it is blocked, rows ordered from top to bottom.
+///
+/// The mask is mapped onto the frame like a [`background::Background`] plate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OcclusionMask {
+    width: u32,
+    height: u32,
+    visibility: Vec<f32>,
+}
+
+impl OcclusionMask {
+    pub fn new(width: u32, height: u32, visibility: Vec<f32>) -> Result<Self, LfgError> {
+        if width == 0 || height == 0 || visibility.len() != width as usize * height as usize {
+            return Err(LfgError::InvalidEffectValue("occlusion mask size".into()));
+        }
+
+        Ok(Self { width, height, visibility })
+    }
+
+    /// Loads the visibility from `channel` of an image, `invert` is for mattes that are white where the light is blocked.
+    pub fn load<P: AsRef<Path>>(path: P, channel: MaskChannel, invert: bool) -> Result<Self, LfgError> {
+        let path = path.as_ref();
+        let is_exr = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("exr"));
+
+        let (width, height, rgba) = match is_exr {
+            true => {
+                let image = exr::prelude::read_first_rgba_layer_from_file(
+                    path,
+                    |size, _| (size.width(), vec![[0.0; 4]; size.width() * size.height()]),
+                    |(width, pixels): &mut (usize, Vec<[f32; 4]>), pos, (r, g, b, a): (f32, f32, f32, f32)| {
+                        pixels[pos.y() * *width + pos.x()] = [r, g, b, a];
+                    },
+                )?;
+
+                let size = image.layer_data.size;
+                let (_, pixels) = image.layer_data.channel_data.pixels;
+                (size.width() as u32, size.height() as u32, pixels)
+            }
+            false => background::load_rgba(path)?,
+        };
+
+        let visibility = rgba
+            .iter()
+            .map(|[r, g, b, a]| {
+                let value = match channel {
+                    MaskChannel::Alpha => *a,
+                    MaskChannel::Luminance => 0.2126 * r + 0.7152 * g + 0.0722 * b,
+                };
+
+                match invert {
+                    true => 1.0 - value.clamp(0.0, 1.0),
+                    false => value.clamp(0.0, 1.0),
+                }
+            })
+            .collect();
+
+        Self::new(width, height, visibility)
+    }
+
+    /// Average visibility over a disc around `position`, with `radius` relative to the frame height.
+    pub fn visible_fraction(&self, (pos_x, pos_y): (f32, f32), radius: f32, frame_size: (u32, u32)) -> f32 {
+        let (fraction_x, fraction_y) = background::visible_fraction((self.width, self.height), frame_size);
+        let aspect_ratio = frame_size.0 as f32 / frame_size.1 as f32;
+
+        let golden_angle = PI * (3.0 - 5.0f32.sqrt());
+        let total: f32 = (0..DISC_SAMPLES)
+            .map(|idx| {
+                let distance = radius * ((idx as f32 + 0.5) / DISC_SAMPLES as f32).sqrt();
+                let angle = idx as f32 * golden_angle;
+
+                let x = pos_x + distance * angle.cos() / aspect_ratio;
+                let y = pos_y + distance * angle.sin();
+
+                // frame coordinates start in the bottom left corner, mask rows at the top
+                self.sample((x - 0.5) * fraction_x + 0.5, (0.5 - y) * fraction_y + 0.5)
+            })
+            .sum();
+
+        total / DISC_SAMPLES as f32
+    }
+
+    /// Scales the intensity of every light of `effect` by its visible fraction.
+    pub fn apply(&self, effect: &mut Effect, frame_size: (u32, u32)) {
+        let radius = effect.occlusion_radius;
+        for light in &mut effect.lights {
+            light.intensity *= self.visible_fraction(light.position(), radius, frame_size);
+        }
+    }
+
+    // bilinear, clamped to the edges
+    fn sample(&self, u: f32, v: f32) -> f32 {
+        let width = self.width as usize;
+        sampling::bilinear((width, self.height as usize), (u, v), Edge::Clamp, |x, y| self.visibility[y * width + x])
+    }
+}
@@ -0,0 +1,1043 @@
+use std::{convert::TryFrom, fs, num::NonZeroU8, path::Path, str::FromStr};
+
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::{
+    animation::{Animatable, Animation, FlareTracks, GhostTracks, Interpolation, LightTracks, Track},
+    effect::{ApertureShape, Effect},
+    flare::{Anamorphic, Flare, FlareStyle, Streaks, MAX_STREAKS},
+    ghost::{DispersionCenter, Ghost, GhostKind, GhostResponse},
+    light::Light,
+    occlusion,
+    spectrum::Spectrum,
+    starburst::{Starburst, MAX_SCRATCHES, MAX_WAVELENGTHS},
+    LfgError,
+};
+
+/// Version 1 presets, with a single flare position, and version 2 presets, with the starburst as an option
+/// of the normal flare, are still loaded and upgraded.
+///
+/// Every field of the current version is required. Bump it whenever the schema changes, keeping a frozen copy of
+/// the previous schema to upgrade from.
+pub const PRESET_VERSION: u32 = 3;
+pub const MAX_SAMPLES: u16 = 1024;
+
+/// Human-editable, versioned snapshot of an [`Effect`], stored as RON.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Preset {
+    pub version: u32,
+    pub name: String,
+    pub effect: Effect,
+    #[serde(default, skip_serializing_if = "Animation::is_empty")]
+    pub animation: Animation,
+}
+
+// only used to check the version, before the strict parse of the whole file
+#[derive(Deserialize)]
+struct PresetHeader {
+    version: u32,
+}
+
+impl Preset {
+    pub fn new<S: Into<String>>(name: S, effect: Effect) -> Self {
+        Self {
+            version: PRESET_VERSION,
+            name: name.into(),
+            effect,
+            animation: Animation::default(),
+        }
+    }
+
+    /// The effect with its animated parameters sampled at `time`, in frames.
+    pub fn effect_at(&self, time: f32) -> Effect {
+        self.animation.sample(&self.effect, time)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, LfgError> {
+        fs::read_to_string(path)?.parse()
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), LfgError> {
+        fs::write(path, self.to_ron()?)?;
+        Ok(())
+    }
+
+    pub fn to_ron(&self) -> Result<String, LfgError> {
+        self.validate()?;
+        Ok(ron::ser::to_string_pretty(self, PrettyConfig::new())?)
+    }
+
+    pub fn validate(&self) -> Result<(), LfgError> {
+        if self.version != PRESET_VERSION {
+            return Err(LfgError::PresetVersion(self.version, PRESET_VERSION));
+        }
+
+        validate_effect(&self.effect)?;
+        validate_animation(&self.animation, &self.effect)
+    }
+}
+
+impl FromStr for Preset {
+    type Err = LfgError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let header: PresetHeader = ron::from_str(s)?;
+        let preset: Preset = match header.version {
+            1 => ron::from_str::<PresetV1>(s)?.into(),
+            2 => ron::from_str::<PresetV2>(s)?.into(),
+            PRESET_VERSION => ron::from_str(s)?,
+            version => return Err(LfgError::PresetVersion(version, PRESET_VERSION)),
+        };
+        preset.validate()?;
+
+        Ok(preset)
+    }
+}
+
+/// Deserializes an option that has to be present, serde would otherwise read a missing field as `None`.
+pub(crate) fn required_option<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::deserialize(deserializer)
+}
+
+fn validate_effect(effect: &Effect) -> Result<(), LfgError> {
+    validate_flare("flare", &effect.flare)?;
+
+    for (idx, ghost) in effect.ghosts.iter().enumerate() {
+        validate_ghost(&format!("ghosts[{}]", idx), ghost)?;
+    }
+
+    for (idx, light) in effect.lights.iter().enumerate() {
+        validate_light(&format!("lights[{}]", idx), light)?;
+    }
+
+    finite("rotation", effect.rotation)?;
+    non_negative("occlusion_radius", effect.occlusion_radius)?;
+
+    validate_aperture("aperture_shape", effect.aperture_shape)?;
+
+    if effect.samples == 0 || effect.samples > MAX_SAMPLES {
+        return Err(invalid("samples", format!("{}, expected 1 to {}", effect.samples, MAX_SAMPLES)));
+    }
+
+    let [shortest, longest] = effect.spectrum.range;
+    positive("spectrum.range[0]", shortest)?;
+    finite("spectrum.range[1]", longest)?;
+    if longest <= shortest {
+        return Err(invalid(
+            "spectrum.range",
+            format!("{:?}, expected the shortest wavelength first", effect.spectrum.range),
+        ));
+    }
+
+    Ok(())
+}
+
+fn validate_light(name: &str, light: &Light) -> Result<(), LfgError> {
+    finite(&format!("{}.pos_x", name), light.pos_x)?;
+    finite(&format!("{}.pos_y", name), light.pos_y)?;
+    non_negative(&format!("{}.intensity", name), light.intensity)?;
+    tint(&format!("{}.tint", name), light.tint)?;
+
+    Ok(())
+}
+
+fn validate_flare(name: &str, flare: &Flare) -> Result<(), LfgError> {
+    color(&format!("{}.color", name), flare.color)?;
+    non_negative(&format!("{}.intensity", name), flare.intensity)?;
+    non_negative(&format!("{}.size", name), flare.size)?;
+    non_negative(&format!("{}.ray_intensity", name), flare.ray_intensity)?;
+    finite(&format!("{}.rotation", name), flare.rotation)?;
+
+    validate_anamorphic(&format!("{}.anamorphic", name), &flare.anamorphic)?;
+
+    positive(&format!("{}.glow.radius", name), flare.glow.radius)?;
+    non_negative(&format!("{}.glow.falloff", name), flare.glow.falloff)?;
+
+    validate_streaks(&format!("{}.streaks", name), &flare.streaks)?;
+    validate_starburst(&format!("{}.starburst", name), &flare.starburst)
+}
+
+fn validate_anamorphic(name: &str, anamorphic: &Anamorphic) -> Result<(), LfgError> {
+    positive(&format!("{}.length", name), anamorphic.length)?;
+    non_negative(&format!("{}.thickness", name), anamorphic.thickness)?;
+    positive(&format!("{}.softness", name), anamorphic.softness)?;
+    non_negative(&format!("{}.falloff", name), anamorphic.falloff)?;
+    non_negative(&format!("{}.ring", name), anamorphic.ring)?;
+    tint(&format!("{}.core_color", name), anamorphic.core_color)?;
+    tint(&format!("{}.tip_color", name), anamorphic.tip_color)
+}
+
+fn validate_streaks(name: &str, streaks: &Streaks) -> Result<(), LfgError> {
+    if streaks.angles.len() > MAX_STREAKS {
+        return Err(invalid(
+            &format!("{}.angles", name),
+            format!("{} streaks, expected at most {}", streaks.angles.len(), MAX_STREAKS),
+        ));
+    }
+    for (idx, angle) in streaks.angles.iter().enumerate() {
+        finite(&format!("{}.angles[{}]", name, idx), *angle)?;
+    }
+
+    positive(&format!("{}.thickness", name), streaks.thickness)?;
+    positive(&format!("{}.length", name), streaks.length)
+}
+
+fn validate_starburst(name: &str, starburst: &Starburst) -> Result<(), LfgError> {
+    unit(&format!("{}.dust", name), starburst.dust)?;
+
+    if starburst.scratches > MAX_SCRATCHES {
+        return Err(invalid(
+            &format!("{}.scratches", name),
+            format!("{}, expected at most {}", starburst.scratches, MAX_SCRATCHES),
+        ));
+    }
+    if starburst.wavelengths == 0 || starburst.wavelengths > MAX_WAVELENGTHS {
+        return Err(invalid(
+            &format!("{}.wavelengths", name),
+            format!("{}, expected 1 to {}", starburst.wavelengths, MAX_WAVELENGTHS),
+        ));
+    }
+
+    Ok(())
+}
+
+fn validate_ghost(name: &str, ghost: &Ghost) -> Result<(), LfgError> {
+    color(&format!("{}.color", name), ghost.color)?;
+    finite(&format!("{}.offset", name), ghost.offset)?;
+    finite(&format!("{}.perpendicular_offset", name), ghost.perpendicular_offset)?;
+    non_negative(&format!("{}.size", name), ghost.size)?;
+    finite(&format!("{}.dispersion", name), ghost.dispersion)?;
+    finite(&format!("{}.distortion", name), ghost.distortion)?;
+    non_negative(&format!("{}.intensity", name), ghost.intensity)?;
+    non_negative(&format!("{}.center_transparency", name), ghost.center_transparency)?;
+
+    finite(&format!("{}.aspect_ratio", name), ghost.aspect_ratio)?;
+    if ghost.aspect_ratio <= 0.0 {
+        return Err(invalid(
+            &format!("{}.aspect_ratio", name),
+            format!("{}, expected a positive number", ghost.aspect_ratio),
+        ));
+    }
+
+    if let Some(shape) = ghost.aperture_shape {
+        validate_aperture(&format!("{}.aperture_shape", name), shape)?;
+    }
+
+    validate_kind(&format!("{}.kind", name), ghost.kind)?;
+
+    let response = &ghost.response;
+    validate_track(&format!("{}.response.intensity", name), &response.intensity, non_negative)?;
+    validate_track(&format!("{}.response.size", name), &response.size, non_negative)?;
+    validate_track(&format!("{}.response.intensity_angle", name), &response.intensity_angle, non_negative)?;
+    validate_track(&format!("{}.response.size_angle", name), &response.size_angle, non_negative)?;
+
+    Ok(())
+}
+
+fn validate_kind(name: &str, kind: GhostKind) -> Result<(), LfgError> {
+    let (width_name, width, max) = match kind {
+        GhostKind::Aperture => return Ok(()),
+        GhostKind::Disc { softness } => return unit(&format!("{}.softness", name), softness),
+        GhostKind::Ring { width } => ("width", width, 1.0),
+        GhostKind::Halo { width } => ("width", width, 0.5),
+        GhostKind::Streak { thickness, falloff } => {
+            non_negative(&format!("{}.falloff", name), falloff)?;
+            return positive(&format!("{}.thickness", name), thickness);
+        }
+    };
+
+    match width > 0.0 && width <= max {
+        true => Ok(()),
+        false => Err(invalid(
+            &format!("{}.{}", name, width_name),
+            format!("{}, expected a number above 0 up to {}", width, max),
+        )),
+    }
+}
+
+fn validate_aperture(name: &str, shape: ApertureShape) -> Result<(), LfgError> {
+    let blades = match shape {
+        ApertureShape::Polygonal(blades) => blades,
+        ApertureShape::Circular => return Ok(()),
+        ApertureShape::Blades {
+            count,
+            curvature,
+            rotation,
+            notch,
+        } => {
+            unit(&format!("{}.curvature", name), curvature)?;
+            finite(&format!("{}.rotation", name), rotation)?;
+            if let Some(notch) = notch {
+                unit(&format!("{}.notch", name), notch)?;
+            }
+            count
+        }
+    };
+
+    ApertureShape::try_from(blades.get()).map_err(|_| invalid(name, format!("{} blades, expected at least 3", blades)))?;
+    Ok(())
+}
+
+fn validate_animation(animation: &Animation, effect: &Effect) -> Result<(), LfgError> {
+    if animation.lights.len() > effect.lights.len() {
+        return Err(invalid(
+            "animation.lights",
+            format!("{} light tracks, the effect has only {} lights", animation.lights.len(), effect.lights.len()),
+        ));
+    }
+    for (idx, tracks) in animation.lights.iter().enumerate() {
+        validate_light_tracks(&format!("animation.lights[{}]", idx), tracks)?;
+    }
+
+    validate_track("animation.rotation", &animation.rotation, finite)?;
+
+    let flare = &animation.flare;
+    validate_track("animation.flare.color", &flare.color, color)?;
+    validate_track("animation.flare.intensity", &flare.intensity, non_negative)?;
+    validate_track("animation.flare.size", &flare.size, non_negative)?;
+    validate_track("animation.flare.ray_intensity", &flare.ray_intensity, non_negative)?;
+    validate_track("animation.flare.rotation", &flare.rotation, finite)?;
+
+    if animation.ghosts.len() > effect.ghosts.len() {
+        return Err(invalid(
+            "animation.ghosts",
+            format!("{} ghost tracks, the effect has only {} ghosts", animation.ghosts.len(), effect.ghosts.len()),
+        ));
+    }
+    for (idx, tracks) in animation.ghosts.iter().enumerate() {
+        validate_ghost_tracks(&format!("animation.ghosts[{}]", idx), tracks)?;
+    }
+
+    Ok(())
+}
+
+fn validate_light_tracks(name: &str, tracks: &LightTracks) -> Result<(), LfgError> {
+    validate_track(&format!("{}.position", name), &tracks.position, |name, [x, y]| {
+        finite(&format!("{}[0]", name), x)?;
+        finite(&format!("{}[1]", name), y)
+    })?;
+    validate_track(&format!("{}.intensity", name), &tracks.intensity, non_negative)?;
+    validate_track(&format!("{}.tint", name), &tracks.tint, tint)?;
+
+    Ok(())
+}
+
+fn validate_ghost_tracks(name: &str, tracks: &GhostTracks) -> Result<(), LfgError> {
+    validate_track(&format!("{}.color", name), &tracks.color, color)?;
+    validate_track(&format!("{}.offset", name), &tracks.offset, finite)?;
+    validate_track(&format!("{}.perpendicular_offset", name), &tracks.perpendicular_offset, finite)?;
+    validate_track(&format!("{}.size", name), &tracks.size, non_negative)?;
+    validate_track(&format!("{}.dispersion", name), &tracks.dispersion, finite)?;
+    validate_track(&format!("{}.distortion", name), &tracks.distortion, finite)?;
+    validate_track(&format!("{}.intensity", name), &tracks.intensity, non_negative)?;
+    validate_track(&format!("{}.center_transparency", name), &tracks.center_transparency, non_negative)?;
+    validate_track(&format!("{}.aspect_ratio", name), &tracks.aspect_ratio, |name, value| {
+        finite(name, value)?;
+        match value > 0.0 {
+            true => Ok(()),
+            false => Err(invalid(name, format!("{}, expected a positive number", value))),
+        }
+    })?;
+
+    Ok(())
+}
+
+fn validate_track<T, F>(name: &str, track: &Track<T>, validate_value: F) -> Result<(), LfgError>
+where
+    T: Animatable,
+    F: Fn(&str, T) -> Result<(), LfgError>,
+{
+    let mut prev_time = None;
+    for (idx, key) in track.keys.iter().enumerate() {
+        let key_name = format!("{}[{}]", name, idx);
+
+        let time_name = format!("{}.time", key_name);
+        finite(&time_name, key.time)?;
+        if let Some(prev_time) = prev_time {
+            if key.time <= prev_time {
+                return Err(invalid(&time_name, format!("{}, expected a time after {}", key.time, prev_time)));
+            }
+        }
+        prev_time = Some(key.time);
+
+        if let Interpolation::Bezier(handles) = key.interpolation {
+            let handles_name = format!("{}.interpolation", key_name);
+            for (component, handle) in handles.iter().enumerate() {
+                finite(&format!("{}[{}]", handles_name, component), *handle)?;
+            }
+            if !(0.0..=1.0).contains(&handles[0]) || !(0.0..=1.0).contains(&handles[2]) {
+                return Err(invalid(&handles_name, "expected the x control points in [0, 1]".into()));
+            }
+        }
+
+        validate_value(&format!("{}.value", key_name), key.value)?;
+    }
+
+    Ok(())
+}
+
+fn finite(name: &str, value: f32) -> Result<(), LfgError> {
+    match value.is_finite() {
+        true => Ok(()),
+        false => Err(invalid(name, format!("{}, expected a finite number", value))),
+    }
+}
+
+fn non_negative(name: &str, value: f32) -> Result<(), LfgError> {
+    finite(name, value)?;
+    match value >= 0.0 {
+        true => Ok(()),
+        false => Err(invalid(name, format!("{}, expected a non-negative number", value))),
+    }
+}
+
+fn positive(name: &str, value: f32) -> Result<(), LfgError> {
+    finite(name, value)?;
+    match value > 0.0 {
+        true => Ok(()),
+        false => Err(invalid(name, format!("{}, expected a positive number", value))),
+    }
+}
+
+fn unit(name: &str, value: f32) -> Result<(), LfgError> {
+    match (0.0..=1.0).contains(&value) {
+        true => Ok(()),
+        false => Err(invalid(name, format!("{}, expected a number from 0 to 1", value))),
+    }
+}
+
+fn color(name: &str, value: [f32; 4]) -> Result<(), LfgError> {
+    for (channel, component) in value.iter().enumerate() {
+        non_negative(&format!("{}[{}]", name, channel), *component)?;
+    }
+
+    Ok(())
+}
+
+fn tint(name: &str, value: [f32; 3]) -> Result<(), LfgError> {
+    for (channel, component) in value.iter().enumerate() {
+        non_negative(&format!("{}[{}]", name, channel), *component)?;
+    }
+
+    Ok(())
+}
+
+fn invalid(name: &str, reason: String) -> LfgError {
+    LfgError::PresetValue(name.into(), reason)
+}
+
+// version 1 files, with the single flare position stored in the effect, frozen as they were written
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PresetV1 {
+    #[allow(dead_code)]
+    version: u32,
+    name: String,
+    effect: EffectV1,
+    #[serde(default)]
+    animation: AnimationV1,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EffectV1 {
+    flare: FlareV1,
+    ghosts: Vec<GhostV1>,
+    rotation: f32,
+    aperture_shape: ApertureShapeV1,
+    pos_x: f32,
+    pos_y: f32,
+    samples: u16,
+    tonemap: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FlareV1 {
+    color: [f32; 4],
+    intensity: f32,
+    size: f32,
+    ray_intensity: f32,
+    style: FlareStyleV1,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+enum FlareStyleV1 {
+    Normal,
+    Anamorphic,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GhostV1 {
+    color: [f32; 4],
+    offset: f32,
+    perpendicular_offset: f32,
+    size: f32,
+    dispersion: f32,
+    distortion: f32,
+    intensity: f32,
+    center_transparency: f32,
+    aspect_ratio: f32,
+    dispersion_center: DispersionCenter,
+}
+
+#[derive(Deserialize)]
+enum ApertureShapeV1 {
+    Polygonal(NonZeroU8),
+    Circular,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct AnimationV1 {
+    position: Track<[f32; 2]>,
+    rotation: Track<f32>,
+    flare: FlareTracksV1,
+    ghosts: Vec<GhostTracks>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FlareTracksV1 {
+    color: Track<[f32; 4]>,
+    intensity: Track<f32>,
+    size: Track<f32>,
+    ray_intensity: Track<f32>,
+}
+
+impl From<PresetV1> for Preset {
+    fn from(v1: PresetV1) -> Self {
+        let EffectV1 {
+            flare,
+            ghosts,
+            rotation,
+            aperture_shape,
+            pos_x,
+            pos_y,
+            samples,
+            tonemap,
+        } = v1.effect;
+
+        let effect = Effect {
+            flare: flare.into(),
+            ghosts: ghosts.into_iter().map(Ghost::from).collect(),
+            rotation,
+            aperture_shape: aperture_shape.into(),
+            lights: vec![Light::at_position((pos_x, pos_y))],
+            occlusion_radius: occlusion::DEFAULT_RADIUS,
+            samples,
+            spectrum: Spectrum::new(),
+            tonemap,
+        };
+
+        let AnimationV1 {
+            position,
+            rotation,
+            flare,
+            ghosts,
+        } = v1.animation;
+
+        let lights = match position.is_empty() {
+            true => Vec::new(),
+            false => vec![LightTracks {
+                position,
+                ..LightTracks::default()
+            }],
+        };
+
+        let animation = Animation {
+            lights,
+            rotation,
+            flare: flare.into(),
+            ghosts,
+        };
+
+        Self {
+            animation,
+            ..Preset::new(v1.name, effect)
+        }
+    }
+}
+
+impl From<FlareV1> for Flare {
+    fn from(v1: FlareV1) -> Self {
+        Flare {
+            color: v1.color,
+            intensity: v1.intensity,
+            size: v1.size,
+            ray_intensity: v1.ray_intensity,
+            style: v1.style.into(),
+            ..Flare::new()
+        }
+    }
+}
+
+impl From<FlareStyleV1> for FlareStyle {
+    fn from(v1: FlareStyleV1) -> Self {
+        match v1 {
+            FlareStyleV1::Normal => FlareStyle::Normal,
+            FlareStyleV1::Anamorphic => FlareStyle::Anamorphic,
+        }
+    }
+}
+
+impl From<GhostV1> for Ghost {
+    fn from(v1: GhostV1) -> Self {
+        Ghost {
+            color: v1.color,
+            offset: v1.offset,
+            perpendicular_offset: v1.perpendicular_offset,
+            size: v1.size,
+            dispersion: v1.dispersion,
+            distortion: v1.distortion,
+            intensity: v1.intensity,
+            center_transparency: v1.center_transparency,
+            aspect_ratio: v1.aspect_ratio,
+            dispersion_center: v1.dispersion_center,
+            ..Ghost::new()
+        }
+    }
+}
+
+impl From<ApertureShapeV1> for ApertureShape {
+    fn from(v1: ApertureShapeV1) -> Self {
+        match v1 {
+            ApertureShapeV1::Polygonal(blades) => ApertureShape::Polygonal(blades),
+            ApertureShapeV1::Circular => ApertureShape::Circular,
+        }
+    }
+}
+
+impl From<FlareTracksV1> for FlareTracks {
+    fn from(v1: FlareTracksV1) -> Self {
+        FlareTracks {
+            color: v1.color,
+            intensity: v1.intensity,
+            size: v1.size,
+            ray_intensity: v1.ray_intensity,
+            ..FlareTracks::default()
+        }
+    }
+}
+
+// version 2 files, with the starburst of the normal flare style stored as an option, frozen as they were last
+// written. The fields with defaults were added while version 2 was current, so older version 2 files lack them.
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PresetV2 {
+    #[allow(dead_code)]
+    version: u32,
+    name: String,
+    effect: EffectV2,
+    #[serde(default)]
+    animation: AnimationV2,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EffectV2 {
+    flare: FlareV2,
+    ghosts: Vec<GhostV2>,
+    rotation: f32,
+    aperture_shape: ApertureShapeV2,
+    lights: Vec<Light>,
+    #[serde(default = "default_occlusion_radius")]
+    occlusion_radius: f32,
+    samples: u16,
+    tonemap: bool,
+}
+
+fn default_occlusion_radius() -> f32 {
+    occlusion::DEFAULT_RADIUS
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FlareV2 {
+    color: [f32; 4],
+    intensity: f32,
+    size: f32,
+    ray_intensity: f32,
+    style: FlareStyleV1,
+    #[serde(default)]
+    starburst: Option<StarburstV2>,
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct StarburstV2 {
+    dust: f32,
+    scratches: u32,
+    seed: u64,
+    wavelengths: u32,
+}
+
+impl Default for StarburstV2 {
+    fn default() -> Self {
+        let Starburst {
+            dust,
+            scratches,
+            seed,
+            wavelengths,
+        } = Starburst::new();
+
+        Self {
+            dust,
+            scratches,
+            seed,
+            wavelengths,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GhostV2 {
+    #[serde(default)]
+    name: String,
+    color: [f32; 4],
+    offset: f32,
+    perpendicular_offset: f32,
+    size: f32,
+    dispersion: f32,
+    distortion: f32,
+    intensity: f32,
+    center_transparency: f32,
+    aspect_ratio: f32,
+    dispersion_center: DispersionCenter,
+    #[serde(default)]
+    kind: GhostKind,
+    #[serde(default)]
+    aperture_shape: Option<ApertureShapeV2>,
+    #[serde(default)]
+    response: GhostResponseV2,
+    #[serde(default)]
+    muted: bool,
+    #[serde(default)]
+    solo: bool,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct GhostResponseV2 {
+    intensity: Track<f32>,
+    size: Track<f32>,
+    intensity_angle: Track<f32>,
+    size_angle: Track<f32>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+enum ApertureShapeV2 {
+    Polygonal(NonZeroU8),
+    Circular,
+    Blades {
+        count: NonZeroU8,
+        curvature: f32,
+        rotation: f32,
+        #[serde(default)]
+        notch: Option<f32>,
+    },
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct AnimationV2 {
+    lights: Vec<LightTracks>,
+    rotation: Track<f32>,
+    flare: FlareTracksV1,
+    ghosts: Vec<GhostTracks>,
+}
+
+impl From<PresetV2> for Preset {
+    fn from(v2: PresetV2) -> Self {
+        let EffectV2 {
+            flare,
+            ghosts,
+            rotation,
+            aperture_shape,
+            lights,
+            occlusion_radius,
+            samples,
+            tonemap,
+        } = v2.effect;
+
+        // the starburst replaced the rays of the normal style, which is now a style of its own
+        let style = match (flare.style.into(), &flare.starburst) {
+            (FlareStyle::Normal, Some(_)) => FlareStyle::Starburst,
+            (style, _) => style,
+        };
+        let flare = Flare {
+            color: flare.color,
+            intensity: flare.intensity,
+            size: flare.size,
+            ray_intensity: flare.ray_intensity,
+            style,
+            starburst: flare.starburst.unwrap_or_default().into(),
+            ..Flare::new()
+        };
+
+        let effect = Effect {
+            flare,
+            ghosts: ghosts.into_iter().map(Ghost::from).collect(),
+            rotation,
+            aperture_shape: aperture_shape.into(),
+            lights,
+            occlusion_radius,
+            samples,
+            spectrum: Spectrum::new(),
+            tonemap,
+        };
+
+        let AnimationV2 {
+            lights,
+            rotation,
+            flare,
+            ghosts,
+        } = v2.animation;
+
+        let animation = Animation {
+            lights,
+            rotation,
+            flare: flare.into(),
+            ghosts,
+        };
+
+        Self {
+            animation,
+            ..Preset::new(v2.name, effect)
+        }
+    }
+}
+
+impl From<StarburstV2> for Starburst {
+    fn from(v2: StarburstV2) -> Self {
+        Starburst {
+            dust: v2.dust,
+            scratches: v2.scratches,
+            seed: v2.seed,
+            wavelengths: v2.wavelengths,
+        }
+    }
+}
+
+impl From<GhostV2> for Ghost {
+    fn from(v2: GhostV2) -> Self {
+        let GhostResponseV2 {
+            intensity,
+            size,
+            intensity_angle,
+            size_angle,
+        } = v2.response;
+
+        Ghost {
+            name: v2.name,
+            color: v2.color,
+            offset: v2.offset,
+            perpendicular_offset: v2.perpendicular_offset,
+            size: v2.size,
+            dispersion: v2.dispersion,
+            distortion: v2.distortion,
+            intensity: v2.intensity,
+            center_transparency: v2.center_transparency,
+            aspect_ratio: v2.aspect_ratio,
+            dispersion_center: v2.dispersion_center,
+            kind: v2.kind,
+            aperture_shape: v2.aperture_shape.map(ApertureShape::from),
+            response: GhostResponse {
+                intensity,
+                size,
+                intensity_angle,
+                size_angle,
+            },
+            muted: v2.muted,
+            solo: v2.solo,
+        }
+    }
+}
+
+impl From<ApertureShapeV2> for ApertureShape {
+    fn from(v2: ApertureShapeV2) -> Self {
+        match v2 {
+            ApertureShapeV2::Polygonal(blades) => ApertureShape::Polygonal(blades),
+            ApertureShapeV2::Circular => ApertureShape::Circular,
+            ApertureShapeV2::Blades {
+                count,
+                curvature,
+                rotation,
+                notch,
+            } => ApertureShape::Blades {
+                count,
+                curvature,
+                rotation,
+                notch,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU8;
+
+    use super::*;
+
+    fn preset_with_variants() -> Preset {
+        let mut effect = Effect::new();
+        effect.aperture_shape = ApertureShape::Blades {
+            count: NonZeroU8::new(6).unwrap(),
+            curvature: 0.25,
+            rotation: 0.5,
+            notch: None,
+        };
+        effect.ghosts[0].kind = GhostKind::Disc { softness: 0.25 };
+
+        Preset::new("variants", effect)
+    }
+
+    #[test]
+    fn round_trip() {
+        let preset = preset_with_variants();
+        let parsed: Preset = preset.to_ron().unwrap().parse().unwrap();
+
+        assert_eq!(parsed, preset);
+    }
+
+    #[test]
+    fn unknown_fields_rejected() {
+        let ron = preset_with_variants().to_ron().unwrap();
+        let with_field = |field: &str, addition: &str| {
+            assert!(ron.contains(field), "{} not in the preset", field);
+            ron.replacen(field, &format!("{}{}", field, addition), 1).parse::<Preset>()
+        };
+
+        assert!(matches!(with_field("name: \"variants\",", " bogus: 1.0,"), Err(LfgError::PresetParse(_))));
+        assert!(matches!(with_field("tonemap: true,", " bogus: 1.0,"), Err(LfgError::PresetParse(_))));
+        assert!(matches!(with_field("softness: 0.25", ", bogus: 1.0"), Err(LfgError::PresetParse(_))));
+        assert!(matches!(with_field("curvature: 0.25,", " bogus: 1.0,"), Err(LfgError::PresetParse(_))));
+    }
+
+    #[test]
+    fn missing_fields_rejected() {
+        let ron = preset_with_variants().to_ron().unwrap();
+        let without = |field: &str| {
+            let start = ron.find(field).unwrap_or_else(|| panic!("{} not in the preset", field));
+            let end = start + ron[start..].find('\n').unwrap();
+            format!("{}{}", &ron[..start], &ron[end..]).parse::<Preset>()
+        };
+
+        for field in ["occlusion_radius:", "spectrum:", "align_to_light:", "muted:", "notch:"] {
+            assert!(matches!(without(field), Err(LfgError::PresetParse(_))), "{} isn't required", field);
+        }
+    }
+
+    #[test]
+    fn wrong_version_rejected() {
+        let ron = Preset::new("version", Effect::new()).to_ron().unwrap();
+        let current = format!("version: {},", PRESET_VERSION);
+        assert!(ron.contains(&current));
+
+        let result = ron.replacen(&current, "version: 99,", 1).parse::<Preset>();
+        assert!(matches!(result, Err(LfgError::PresetVersion(99, PRESET_VERSION))));
+    }
+
+    #[test]
+    fn invalid_value_rejected() {
+        let mut preset = Preset::new("invalid", Effect::new());
+        preset.effect.ghosts[1].size = -1.0;
+
+        assert!(matches!(preset.to_ron(), Err(LfgError::PresetValue(name, _)) if name == "ghosts[1].size"));
+    }
+
+    #[test]
+    fn v1_upgraded() {
+        let v1 = r#"(
+            version: 1,
+            name: "old",
+            effect: (
+                flare: (color: (1.0, 0.5, 0.5, 1.0), intensity: 1.0, size: 5.0, ray_intensity: 1.0, style: Normal),
+                ghosts: [],
+                rotation: 0.2,
+                aperture_shape: Polygonal(8),
+                pos_x: 0.25,
+                pos_y: 0.75,
+                samples: 8,
+                tonemap: true,
+            ),
+            animation: (
+                position: [(time: 0.0, value: (0.25, 0.75)), (time: 10.0, value: (0.5, 0.5))],
+            ),
+        )"#;
+
+        let preset: Preset = v1.parse().unwrap();
+        assert_eq!(preset.version, PRESET_VERSION);
+        assert_eq!(preset.name, "old");
+        assert_eq!(preset.effect.lights, vec![Light::at_position((0.25, 0.75))]);
+        assert_eq!(preset.animation.lights.len(), 1);
+        assert_eq!(preset.animation.lights[0].position.keys.len(), 2);
+        assert_eq!(preset.effect_at(10.0).lights[0].position(), (0.5, 0.5));
+
+        // fields of later versions didn't exist yet
+        let later_field = v1.replacen("style: Normal", "style: Normal, rotation: 0.5", 1).parse::<Preset>();
+        assert!(matches!(later_field, Err(LfgError::PresetParse(_))));
+        let later_variant = v1.replacen("style: Normal", "style: Starburst", 1).parse::<Preset>();
+        assert!(matches!(later_variant, Err(LfgError::PresetParse(_))));
+    }
+
+    #[test]
+    fn v2_starburst_upgraded() {
+        let v2 = r#"(
+            version: 2,
+            name: "starburst",
+            effect: (
+                flare: (
+                    color: (1.0, 0.5, 0.5, 1.0),
+                    intensity: 1.0,
+                    size: 5.0,
+                    ray_intensity: 1.0,
+                    style: Normal,
+                    starburst: Some((dust: 0.25, scratches: 4, seed: 9, wavelengths: 8)),
+                ),
+                ghosts: [],
+                rotation: 0.2,
+                aperture_shape: Polygonal(6),
+                lights: [(pos_x: 0.25, pos_y: 0.75, intensity: 1.0, tint: (1.0, 1.0, 1.0))],
+                samples: 8,
+                tonemap: true,
+            ),
+        )"#;
+
+        let preset: Preset = v2.parse().unwrap();
+        assert_eq!(preset.version, PRESET_VERSION);
+        assert_eq!(preset.effect.flare.style, FlareStyle::Starburst);
+        assert_eq!(
+            preset.effect.flare.starburst,
+            Starburst {
+                dust: 0.25,
+                scratches: 4,
+                seed: 9,
+                wavelengths: 8
+            }
+        );
+        assert_eq!(preset.effect.occlusion_radius, occlusion::DEFAULT_RADIUS);
+
+        let without_starburst: Preset = v2
+            .replacen("starburst: Some((dust: 0.25, scratches: 4, seed: 9, wavelengths: 8)),", "", 1)
+            .parse()
+            .unwrap();
+        assert_eq!(without_starburst.effect.flare.style, FlareStyle::Normal);
+        assert_eq!(without_starburst.effect.flare.starburst, Starburst::new());
+
+        let unknown = v2.replacen("style: Normal,", "style: Normal, rotation: 0.5,", 1).parse::<Preset>();
+        assert!(matches!(unknown, Err(LfgError::PresetParse(_))));
+    }
+}
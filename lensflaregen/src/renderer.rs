@@ -0,0 +1,264 @@
+use gl_wrapper::{
+    framebuffer::Framebuffer,
+    geometry::{self, Geometry},
+    state::{Blend, State},
+    texture::{Texture2d, TextureFormat},
+};
+
+use crate::{
+    aperture::ApertureImage,
+    background::{self, Background},
+    effect::{ApertureShape, Effect},
+    flare::FlareStyle,
+    ghost,
+    shader_lib::ShaderLib,
+    spectrum::{Spectrum, SPECTRUM_TABLE_LEN},
+    starburst::{Starburst, STARBURST_SIZE},
+    LfgError,
+};
+
+pub(crate) const NOISE_BYTES: &[u8] = include_bytes!("../images/noise.png");
+
+// resolution of an aperture in the shape atlas
+pub(crate) const SHAPE_TILE: u32 = 512;
+
+/// Owns all GL resources needed to draw an [`Effect`] into an HDR buffer.
+///
+/// Requires a current OpenGL 4.5 context, both for construction and for every other call.
+pub struct Renderer {
+    pub(crate) shader_lib: ShaderLib,
+    pub(crate) main_hdr_buf: Framebuffer,
+    pub(crate) quad: Geometry,
+    /// Edge factor and coverage of every polygonal aperture drawn, a square tile each.
+    pub(crate) shape_atlas: Framebuffer,
+    /// Apertures in the order of their tiles, circular apertures need none.
+    pub(crate) shape_tiles: Vec<ApertureShape>,
+    pub(crate) size: (u32, u32),
+    pub(crate) frame_num: u64,
+    /// Replaces the ghost geometry when set.
+    pub(crate) aperture_image: Option<Texture2d>,
+    /// Diffraction pattern of the flare, with the aperture and settings it was generated for.
+    pub(crate) starburst: Option<(ApertureShape, Starburst, Spectrum, Texture2d)>,
+    /// Colors of the dispersion samples, with the spectrum they were evaluated for.
+    spectrum: (Spectrum, Texture2d),
+    noise: Texture2d,
+    background: Option<(Texture2d, (u32, u32))>,
+}
+
+impl Renderer {
+    pub fn new(width: u32, height: u32) -> Result<Self, LfgError> {
+        let shader_lib = ShaderLib::new()?;
+
+        Ok(Self {
+            shader_lib,
+            main_hdr_buf: Framebuffer::hdr(width, height),
+            quad: geometry::quad(),
+            shape_atlas: Framebuffer::rg32f(SHAPE_TILE, SHAPE_TILE),
+            shape_tiles: Vec::new(),
+            size: (width, height),
+            frame_num: 0,
+            aperture_image: None,
+            starburst: None,
+            spectrum: (Spectrum::new(), spectrum_texture(&Spectrum::new())),
+            noise: texture_from_bytes(NOISE_BYTES)?,
+            background: None,
+        })
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        self.size
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        if self.size != (width, height) {
+            self.main_hdr_buf.resize(width, height);
+            self.size = (width, height);
+        }
+    }
+
+    /// Uploads the plate added under the effect, `None` renders onto black.
+    pub fn set_background(&mut self, background: Option<&Background>) {
+        self.background = background.map(|background| {
+            let (width, height) = background.size();
+            let texture = Texture2d::new(width, height, background.pixels(), TextureFormat::RgbF32);
+            texture.set_sampling(gl::LINEAR, gl::CLAMP_TO_EDGE);
+
+            (texture, (width, height))
+        });
+    }
+
+    /// Uploads the aperture image every ghost is shaped by, `None` goes back to the aperture geometry.
+    pub fn set_aperture_image(&mut self, image: Option<&ApertureImage>) {
+        self.aperture_image = image.map(|image| {
+            let (width, height) = image.size();
+            let texture = Texture2d::new(width, height, image.pixels(), TextureFormat::RF32);
+            texture.set_sampling(gl::LINEAR, gl::CLAMP_TO_EDGE);
+
+            texture
+        });
+    }
+
+    /// Advances the frame counter, which drives the dispersion jitter pattern.
+    pub fn next_frame(&mut self) {
+        self.frame_num += 1;
+    }
+
+    /// Draws `effect` into the HDR buffer.
+    pub fn render(&mut self, effect: &Effect) {
+        let mut shapes = Vec::new();
+        for ghost in effect.visible_ghosts() {
+            let shape = effect.ghost_aperture(ghost, self.aperture_image.is_some());
+            if shape != ApertureShape::Circular && !shapes.contains(&shape) {
+                shapes.push(shape);
+            }
+        }
+
+        // only redrawn when the apertures change, like while dragging a blade slider
+        if shapes != self.shape_tiles {
+            self.shape_tiles = shapes;
+            self.draw_shape_atlas();
+        }
+
+        if self.spectrum.0 != effect.spectrum {
+            self.spectrum = (effect.spectrum, spectrum_texture(&effect.spectrum));
+        }
+        self.update_starburst(effect);
+
+        State::viewport(0, 0, self.size.0, self.size.1);
+        State::blend(Blend::Enable(gl::ONE, gl::ONE));
+        self.noise.bind(2);
+        if let Some(texture) = &self.aperture_image {
+            texture.bind(3);
+        }
+        if let Some((_, _, _, texture)) = &self.starburst {
+            texture.bind(4);
+        }
+        self.spectrum.1.bind(5);
+
+        effect.draw(self);
+
+        if let Some((texture, plate_size)) = &self.background {
+            let (fraction_x, fraction_y) = background::visible_fraction(*plate_size, self.size);
+            let Self {
+                shader_lib,
+                main_hdr_buf,
+                quad,
+                ..
+            } = self;
+
+            main_hdr_buf.draw_with(|_fb| {
+                shader_lib.background.bind();
+                shader_lib.background.set_float_uniform("visible_fraction", [fraction_x, fraction_y]);
+                texture.bind(0);
+
+                quad.draw();
+            });
+        }
+    }
+
+    /// Tile of `shape` in the shape atlas, -1 for apertures without one.
+    pub(crate) fn shape_tile(&self, shape: ApertureShape) -> f32 {
+        match self.shape_tiles.iter().position(|tile| *tile == shape) {
+            Some(idx) => idx as f32,
+            None => -1.0,
+        }
+    }
+
+    // the FFT is only run again when the aperture, the starburst settings or the spectrum change
+    fn update_starburst(&mut self, effect: &Effect) {
+        if effect.flare.style != FlareStyle::Starburst {
+            return;
+        }
+
+        let settings = effect.flare.starburst;
+        let shape = effect.aperture_shape;
+        if let Some((cached_shape, cached_settings, cached_spectrum, _)) = &self.starburst {
+            if *cached_shape == shape && *cached_settings == settings && *cached_spectrum == effect.spectrum {
+                return;
+            }
+        }
+
+        let pixels: Vec<f32> = settings.generate(shape, &effect.spectrum).into_iter().flatten().collect();
+        let size = STARBURST_SIZE as u32;
+        let texture = Texture2d::new(size, size, &pixels, TextureFormat::RgbF32);
+        texture.set_sampling(gl::LINEAR, gl::CLAMP_TO_EDGE);
+
+        self.starburst = Some((shape, settings, effect.spectrum, texture));
+    }
+
+    fn draw_shape_atlas(&mut self) {
+        let Self {
+            shader_lib,
+            shape_atlas,
+            shape_tiles,
+            ..
+        } = self;
+
+        shape_atlas.resize(SHAPE_TILE * shape_tiles.len().max(1) as u32, SHAPE_TILE);
+        State::blend(Blend::Disable);
+
+        shape_atlas.draw_with(|fb| {
+            State::viewport(0, 0, SHAPE_TILE * shape_tiles.len().max(1) as u32, SHAPE_TILE);
+            fb.clear();
+            shader_lib.ghost_shape.bind();
+
+            for (idx, shape) in shape_tiles.iter().enumerate() {
+                State::viewport(SHAPE_TILE * idx as u32, 0, SHAPE_TILE, SHAPE_TILE);
+                ghost::gen_ghost_geo(*shape).draw();
+            }
+        });
+    }
+
+    /// Draws the HDR buffer into the default framebuffer.
+    pub fn tonemap(&self, tonemap: bool) {
+        Framebuffer::draw_with_default(|_fb| self.draw_tonemap(tonemap));
+    }
+
+    /// Runs the tonemap pass offscreen and reads back the sRGB encoded result as RGB bytes,
+    /// with rows ordered from top to bottom.
+    pub fn read_tonemapped(&self, tonemap: bool) -> Vec<u8> {
+        let (width, height) = self.size;
+        let mut ldr_buf = Framebuffer::ldr(width, height);
+        ldr_buf.draw_with(|fb| {
+            fb.clear();
+            self.draw_tonemap(tonemap);
+        });
+
+        let pixels = ldr_buf.read_pixels(width, height);
+
+        let row_len = width as usize * 3;
+        pixels.chunks_exact(row_len).rev().flatten().map(|v| (v * 255.0).round() as u8).collect()
+    }
+
+    /// Reads back the linear HDR buffer as RGB triplets, with rows ordered from top to bottom.
+    pub fn read_hdr(&self) -> Vec<f32> {
+        let (width, height) = self.size;
+        let pixels = self.main_hdr_buf.read_pixels(width, height);
+
+        let row_len = width as usize * 3;
+        pixels.chunks_exact(row_len).rev().flatten().copied().collect()
+    }
+
+    fn draw_tonemap(&self, tonemap: bool) {
+        self.shader_lib.tonemap.bind();
+        self.shader_lib.tonemap.set_int_uniform("tonemap", [tonemap as i32]);
+        self.main_hdr_buf.bind_as_color_texture(0);
+
+        self.quad.draw();
+    }
+}
+
+fn texture_from_bytes(bytes: &[u8]) -> Result<Texture2d, LfgError> {
+    let img = image::load_from_memory(bytes)?;
+    let buf = img.to_rgba8();
+
+    Ok(Texture2d::new(buf.width(), buf.height(), buf.as_flat_samples().samples, TextureFormat::Rgba))
+}
+
+fn spectrum_texture(spectrum: &Spectrum) -> Texture2d {
+    let pixels: Vec<f32> = spectrum.table().into_iter().flatten().collect();
+    let texture = Texture2d::new(SPECTRUM_TABLE_LEN as u32, 1, &pixels, TextureFormat::RgbF32);
+    texture.set_sampling(gl::LINEAR, gl::CLAMP_TO_EDGE);
+
+    texture
+}
@@ -0,0 +1,93 @@
+//! Bilinear sampling of the images used on the CPU, matching the GL samplers.
+
+/// Blend from `x` at `a = 0` to `y` at `a = 1`, like GLSL `mix`.
+pub(crate) fn mix(x: f32, y: f32, a: f32) -> f32 {
+    x * (1.0 - a) + y * a
+}
+
+/// Value stored in a single texel.
+pub(crate) trait Texel: Copy {
+    const ZERO: Self;
+
+    fn mix(self, other: Self, a: f32) -> Self;
+}
+
+impl Texel for f32 {
+    const ZERO: Self = 0.0;
+
+    fn mix(self, other: Self, a: f32) -> Self {
+        mix(self, other, a)
+    }
+}
+
+impl Texel for [f32; 3] {
+    const ZERO: Self = [0.0; 3];
+
+    fn mix(self, other: Self, a: f32) -> Self {
+        [0, 1, 2].map(|c| mix(self[c], other[c], a))
+    }
+}
+
+/// How texels outside of the image are read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Edge {
+    /// The nearest texel of the image, like `GL_CLAMP_TO_EDGE`.
+    Clamp,
+    /// The image repeats, like `GL_REPEAT`.
+    Repeat,
+    /// Zero, like `GL_CLAMP_TO_BORDER` with a black border.
+    Zero,
+}
+
+/// Bilinear sample at `(u, v)` of an image of `width` by `height` texels, `texel(x, y)` reading a single texel.
+///
+/// The image spans from 0 to 1 with the texel centers half a texel inside, like `GL_LINEAR` sampling.
+pub(crate) fn bilinear<T: Texel>((width, height): (usize, usize), (u, v): (f32, f32), edge: Edge, texel: impl Fn(usize, usize) -> T) -> T {
+    let (x, y) = (u * width as f32 - 0.5, v * height as f32 - 0.5);
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let (width, height) = (width as i64, height as i64);
+
+    let read = |tx: i64, ty: i64| match edge {
+        Edge::Clamp => texel(tx.clamp(0, width - 1) as usize, ty.clamp(0, height - 1) as usize),
+        Edge::Repeat => texel(tx.rem_euclid(width) as usize, ty.rem_euclid(height) as usize),
+        Edge::Zero => match (0..width).contains(&tx) && (0..height).contains(&ty) {
+            true => texel(tx as usize, ty as usize),
+            false => T::ZERO,
+        },
+    };
+
+    let (x0, y0) = (x0 as i64, y0 as i64);
+    let first_row = read(x0, y0).mix(read(x0 + 1, y0), fx);
+    let second_row = read(x0, y0 + 1).mix(read(x0 + 1, y0 + 1), fx);
+    first_row.mix(second_row, fy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // two texels in a row, 0 on the left and 1 on the right
+    fn sample(u: f32, edge: Edge) -> f32 {
+        bilinear((2, 1), (u, 0.5), edge, |x, _| x as f32)
+    }
+
+    #[test]
+    fn texel_centers_are_exact() {
+        for edge in [Edge::Clamp, Edge::Repeat, Edge::Zero] {
+            assert_eq!(sample(0.25, edge), 0.0);
+            assert_eq!(sample(0.75, edge), 1.0);
+            assert_eq!(sample(0.5, edge), 0.5);
+        }
+    }
+
+    #[test]
+    fn edges_read_outside_texels() {
+        // a quarter texel outside of the outer texel centers, blending in the texels beyond the edges
+        assert_eq!(sample(0.875, Edge::Clamp), 1.0);
+        assert_eq!(sample(0.875, Edge::Repeat), 0.75);
+        assert_eq!(sample(0.875, Edge::Zero), 0.75);
+        assert_eq!(sample(0.125, Edge::Repeat), 0.25);
+        assert_eq!(sample(0.125, Edge::Zero), 0.0);
+    }
+}
@@ -0,0 +1,86 @@
+use gl_wrapper::shader::{Shader, ShaderBuilder, ShaderCompilationError};
+
+use crate::flare::FlareStyle;
+
+const COMMON_SHADER: &str = include_str!("../shaders/common.glsl");
+
+const QUAD_VERT: &str = include_str!("../shaders/quad.vert");
+const FLARE_FRAG: &str = include_str!("../shaders/flare.frag");
+
+const GHOST_VERT: &str = include_str!("../shaders/ghost.vert");
+const GHOST_FRAG: &str = include_str!("../shaders/ghost.frag");
+
+const TONEMAP: &str = include_str!("../shaders/tonemap.frag");
+const GHOSTS: &str = include_str!("../shaders/ghosts.frag");
+const BACKGROUND: &str = include_str!("../shaders/background.frag");
+
+pub struct ShaderLib {
+    pub flare: Shader,
+    pub flare_anam: Shader,
+    pub flare_glow: Shader,
+    pub flare_streaks: Shader,
+    pub flare_starburst: Shader,
+    /// Aperture fans drawn into the shape atlas.
+    pub ghost_shape: Shader,
+    /// Every ghost of a light, dispersed.
+    pub ghosts: Shader,
+    pub tonemap: Shader,
+    pub background: Shader,
+}
+
+impl ShaderLib {
+    pub fn new() -> Result<Self, ShaderCompilationError> {
+        let flare = ShaderBuilder::new(QUAD_VERT, FLARE_FRAG).with_common_code(COMMON_SHADER).build()?;
+        let flare_anam = ShaderBuilder::new(QUAD_VERT, FLARE_FRAG)
+            .with_common_code(COMMON_SHADER)
+            .with_define("ANAMORPHIC")
+            .build()?;
+        let flare_glow = ShaderBuilder::new(QUAD_VERT, FLARE_FRAG)
+            .with_common_code(COMMON_SHADER)
+            .with_define("GLOW")
+            .build()?;
+        let flare_streaks = ShaderBuilder::new(QUAD_VERT, FLARE_FRAG)
+            .with_common_code(COMMON_SHADER)
+            .with_define("STREAKS")
+            .build()?;
+        let flare_starburst = ShaderBuilder::new(QUAD_VERT, FLARE_FRAG)
+            .with_common_code(COMMON_SHADER)
+            .with_define("STARBURST")
+            .build()?;
+        let ghost_shape = ShaderBuilder::new(GHOST_VERT, GHOST_FRAG).with_common_code(COMMON_SHADER).build()?;
+        let ghosts = ShaderBuilder::new(QUAD_VERT, GHOSTS).with_common_code(COMMON_SHADER).build()?;
+        let tonemap = ShaderBuilder::new(QUAD_VERT, TONEMAP).with_common_code(COMMON_SHADER).build()?;
+        let background = ShaderBuilder::new(QUAD_VERT, BACKGROUND).with_common_code(COMMON_SHADER).build()?;
+
+        let lib = Self {
+            flare,
+            flare_anam,
+            flare_glow,
+            flare_streaks,
+            flare_starburst,
+            ghost_shape,
+            ghosts,
+            tonemap,
+            background,
+        };
+
+        Ok(lib)
+    }
+
+    /// Variant of the flare shader drawing `style`.
+    pub fn flare_variant(&self, style: FlareStyle) -> &Shader {
+        match style {
+            FlareStyle::Normal => &self.flare,
+            FlareStyle::Anamorphic => &self.flare_anam,
+            FlareStyle::Glow => &self.flare_glow,
+            FlareStyle::Streaks => &self.flare_streaks,
+            FlareStyle::Starburst => &self.flare_starburst,
+        }
+    }
+}
+
+impl Default for ShaderLib {
+    fn default() -> Self {
+        Self::new().unwrap()
+    }
+}
@@ -0,0 +1,230 @@
+//! Conversion of the sampled wavelengths to RGB, for the dispersion of ghosts and the starburst.
+
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+const SPECTRAL_BYTES: &[u8] = include_bytes!("../images/spectral.png");
+// wavelengths at the left and right edge of `images/spectral.png`, which has no scale of its own, chosen so
+// the peaks of its channels line up with the CIE response
+const LOOKUP_RANGE: [f32; 2] = [300.0, 775.0];
+
+/// Entries of the table passed to the ghost shader, evenly spread over the sampled range.
+pub const SPECTRUM_TABLE_LEN: usize = 256;
+
+/// Wavelengths the dispersion samples span and how they are turned into colors.
+///
+/// Every sample is weighted by its color, and the weights of each channel add up to one, so dispersion spreads
+/// a ghost into a spectrum without changing its overall color.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Spectrum {
+    /// Shortest and longest wavelength in nanometers, sampled from the inner to the outer edge of a ghost.
+    pub range: [f32; 2],
+    pub response: SpectralResponse,
+}
+
+impl Spectrum {
+    pub fn new() -> Self {
+        Self {
+            range: [400.0, 700.0],
+            response: SpectralResponse::Cie,
+        }
+    }
+
+    /// Wavelength at `x`, from 0 at the start of the range to 1 at its end.
+    pub fn wavelength(&self, x: f32) -> f32 {
+        self.range[0] + (self.range[1] - self.range[0]) * x
+    }
+
+    /// Linear RGB of `wavelength` in nanometers, without negative components.
+    pub fn rgb(&self, wavelength: f32) -> [f32; 3] {
+        match self.response {
+            SpectralResponse::Cie => cie_rgb(wavelength),
+            SpectralResponse::Lookup => lookup_rgb(wavelength),
+        }
+    }
+
+    /// Colors at the centers of `SPECTRUM_TABLE_LEN` equal parts of the range, like texels of a texture.
+    pub fn table(&self) -> Vec<[f32; 3]> {
+        (0..SPECTRUM_TABLE_LEN)
+            .map(|idx| self.rgb(self.wavelength((idx as f32 + 0.5) / SPECTRUM_TABLE_LEN as f32)))
+            .collect()
+    }
+}
+
+impl Default for Spectrum {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SpectralResponse {
+    /// CIE 1931 color matching functions, converted to linear sRGB.
+    Cie,
+    /// Colors of the spectrum image shipped in `images/spectral.png`.
+    Lookup,
+}
+
+impl SpectralResponse {
+    pub const NAMES: [&'static str; 2] = ["CIE 1931", "Spectral image"];
+
+    /// Response at `idx` of [`SpectralResponse::NAMES`].
+    pub fn from_index(idx: usize) -> Option<Self> {
+        match idx {
+            0 => Some(SpectralResponse::Cie),
+            1 => Some(SpectralResponse::Lookup),
+            _ => None,
+        }
+    }
+
+    pub fn index(&self) -> usize {
+        match self {
+            SpectralResponse::Cie => 0,
+            SpectralResponse::Lookup => 1,
+        }
+    }
+}
+
+/// Linear interpolation in a table made by [`Spectrum::table`], clamped at its ends like the GL sampler.
+pub(crate) fn sample_table(table: &[[f32; 3]], x: f32) -> [f32; 3] {
+    let pos = (x * table.len() as f32 - 0.5).clamp(0.0, (table.len() - 1) as f32);
+    let (idx, fract) = (pos.floor() as usize, pos.fract());
+    let (low, high) = (table[idx], table[(idx + 1).min(table.len() - 1)]);
+
+    [0, 1, 2].map(|c| low[c] * (1.0 - fract) + high[c] * fract)
+}
+
+fn cie_rgb(wavelength: f32) -> [f32; 3] {
+    let [x, y, z] = cie_xyz(wavelength);
+
+    [
+        (3.2406 * x - 1.5372 * y - 0.4986 * z).max(0.0),
+        (-0.9689 * x + 1.8758 * y + 0.0415 * z).max(0.0),
+        (0.0557 * x - 0.2040 * y + 1.0570 * z).max(0.0),
+    ]
+}
+
+// multi-lobe gaussian fit of the CIE 1931 2° observer by Wyman, Sloan and Shirley
+fn cie_xyz(wavelength: f32) -> [f32; 3] {
+    let lobe = |center: f32, below: f32, above: f32| {
+        let width = if wavelength < center { below } else { above };
+        (-0.5 * ((wavelength - center) / width).powi(2)).exp()
+    };
+
+    let x = 1.056 * lobe(599.8, 37.9, 31.0) + 0.362 * lobe(442.0, 16.0, 26.7) - 0.065 * lobe(501.1, 20.4, 26.2);
+    let y = 0.821 * lobe(568.8, 46.9, 40.5) + 0.286 * lobe(530.9, 16.3, 31.1);
+    let z = 1.217 * lobe(437.0, 11.8, 36.0) + 0.681 * lobe(459.0, 26.0, 13.8);
+
+    [x, y, z]
+}
+
+fn lookup_rgb(wavelength: f32) -> [f32; 3] {
+    static LOOKUP: OnceLock<Vec<[f32; 3]>> = OnceLock::new();
+
+    let lookup = LOOKUP.get_or_init(|| {
+        let image = image::load_from_memory(SPECTRAL_BYTES)
+            .expect("the shipped spectral image is a valid png")
+            .to_rgb8();
+        let row = image.height() / 2;
+        (0..image.width())
+            .map(|x| image.get_pixel(x, row).0.map(|c| srgb_to_linear(c as f32 / 255.0)))
+            .collect()
+    });
+
+    let x = (wavelength - LOOKUP_RANGE[0]) / (LOOKUP_RANGE[1] - LOOKUP_RANGE[0]);
+    match (0.0..=1.0).contains(&x) {
+        true => sample_table(lookup, x),
+        false => [0.0; 3],
+    }
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    match value <= 0.04045 {
+        true => value / 12.92,
+        false => ((value + 0.055) / 1.055).powf(2.4),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VISIBLE: [f32; 2] = [380.0, 780.0];
+
+    fn visible_wavelengths() -> impl Iterator<Item = f32> {
+        (VISIBLE[0] as u32..=VISIBLE[1] as u32).map(|wavelength| wavelength as f32)
+    }
+
+    #[test]
+    fn cie_fit_balances_equal_energy() {
+        // the CIE 1931 functions are scaled so a flat spectrum has equal X, Y and Z, about 106.86 at 1 nm steps
+        let mut sums = [0.0; 3];
+        for wavelength in visible_wavelengths() {
+            sums.iter_mut().zip(&cie_xyz(wavelength)).for_each(|(sum, value)| *sum += value);
+        }
+
+        for (channel, sum) in sums.iter().enumerate() {
+            assert!((sum / 106.86 - 1.0).abs() < 0.03, "channel {} integrates to {}", channel, sum);
+        }
+    }
+
+    #[test]
+    fn flat_spectrum_stays_neutral() {
+        // a ghost of the same brightness at every wavelength, weighted and normalized like `ghosts.frag`, with enough
+        // samples that every channel sees some of the range
+        for response in [SpectralResponse::Cie, SpectralResponse::Lookup] {
+            for range in [[400.0, 700.0], VISIBLE] {
+                let table = Spectrum { range, response }.table();
+                for samples in [8, 16, 64] {
+                    let weights: Vec<_> = (0..samples).map(|idx| sample_table(&table, (0.37 + idx as f32) / samples as f32)).collect();
+                    for channel in 0..3 {
+                        let sum: f32 = weights.iter().map(|weight| weight[channel]).sum();
+                        let color: f32 = weights.iter().map(|weight| 0.7 * weight[channel]).sum::<f32>() / sum.max(1e-6);
+                        assert!(
+                            (color - 0.7).abs() < 1e-5,
+                            "{:?} {:?} {} samples, channel {}: {}",
+                            response,
+                            range,
+                            samples,
+                            channel,
+                            color
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn lookup_range_lines_up_with_cie() {
+        let peak = |response: SpectralResponse, channel: usize| {
+            let spectrum = Spectrum { range: VISIBLE, response };
+            visible_wavelengths()
+                .max_by(|a, b| spectrum.rgb(*a)[channel].total_cmp(&spectrum.rgb(*b)[channel]))
+                .unwrap()
+        };
+
+        for channel in 0..3 {
+            let (cie, lookup) = (peak(SpectralResponse::Cie, channel), peak(SpectralResponse::Lookup, channel));
+            assert!(
+                (cie - lookup).abs() <= 15.0,
+                "channel {} peaks at {} nm, the CIE response at {} nm",
+                channel,
+                lookup,
+                cie
+            );
+        }
+    }
+
+    #[test]
+    fn lookup_is_dark_outside_its_range() {
+        let spectrum = Spectrum {
+            range: VISIBLE,
+            response: SpectralResponse::Lookup,
+        };
+        assert_eq!(spectrum.rgb(LOOKUP_RANGE[0] - 1.0), [0.0; 3]);
+        assert_eq!(spectrum.rgb(LOOKUP_RANGE[1] + 1.0), [0.0; 3]);
+    }
+}
@@ -0,0 +1,368 @@
+//! Diffraction starburst of the aperture, the far field pattern of its rasterized shape computed with an FFT.
+
+use std::f64::consts::PI;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    effect::ApertureShape,
+    generator::SplitMix64,
+    ghost,
+    sampling::{self, Edge},
+    spectrum::Spectrum,
+};
+
+/// Width and height of the starburst texture, it spans the frame height on both sides of the light.
+pub const STARBURST_SIZE: usize = 512;
+pub const MAX_SCRATCHES: u32 = 64;
+pub const MAX_WAVELENGTHS: u32 = 64;
+
+// the aperture covers a quarter of the grid, the padding around it sets how finely the pattern is sampled
+const PUPIL_RADIUS: f64 = STARBURST_SIZE as f64 / 8.0;
+// wavelength the pattern is computed at, the others are scaled from it
+const BASE_WAVELENGTH: f32 = 550.0;
+// the pattern is relative to its center, this lifts the spikes to the brightness of the procedural rays
+const GAIN: f32 = 2000.0;
+// the center rolls off towards this value instead, it would otherwise outshine the whole flare
+const CENTER_LIMIT: f32 = 4.0;
+const EDGE_TABLE_LEN: usize = 2048;
+
+/// Diffraction pattern drawn by the starburst flare style.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Starburst {
+    /// Fraction of the aperture covered by dust specks, from 0 to 1.
+    pub dust: f32,
+    /// Scratches across the aperture.
+    pub scratches: u32,
+    /// Seed of the dust and scratch placement.
+    pub seed: u64,
+    /// Wavelengths of the effect spectrum added up into the colored pattern.
+    pub wavelengths: u32,
+}
+
+impl Starburst {
+    pub fn new() -> Self {
+        Self {
+            dust: 0.0,
+            scratches: 0,
+            seed: 0,
+            wavelengths: 16,
+        }
+    }
+
+    /// Colored diffraction pattern of `shape`, `STARBURST_SIZE` squared RGB triplets with rows ordered from
+    /// bottom to top and the light at the center.
+    pub fn generate(&self, shape: ApertureShape, spectrum: &Spectrum) -> Vec<[f32; 3]> {
+        let mut pupil = rasterize_aperture(shape);
+        self.add_dust(&mut pupil);
+        self.add_scratches(&mut pupil);
+
+        let power = power_spectrum(&pupil);
+        self.integrate_wavelengths(&power, spectrum)
+    }
+
+    fn add_dust(&self, pupil: &mut [f64]) {
+        // dust and scratches draw from separate streams, so adding scratches keeps the specks in place
+        let mut rng = SplitMix64(self.seed);
+        let mut covered = 0.0;
+        let target = self.dust.clamp(0.0, 1.0) as f64 * PI;
+
+        while covered < target {
+            let (x, y) = random_in_disc(&mut rng);
+            let radius = 0.005 + 0.025 * rng.next_f32() as f64;
+            covered += PI * radius * radius;
+
+            paint(pupil, (x, y), radius, 1.0, |px, py| ((px - x).powi(2) + (py - y).powi(2)).sqrt() - radius);
+        }
+    }
+
+    fn add_scratches(&self, pupil: &mut [f64]) {
+        let mut rng = SplitMix64(!self.seed);
+
+        for _ in 0..self.scratches {
+            let center = random_in_disc(&mut rng);
+            let angle = rng.next_f32() as f64 * PI;
+            let half_length = 0.2 + 0.6 * rng.next_f32() as f64;
+            let half_width = 0.002 + 0.004 * rng.next_f32() as f64;
+            let (dir_x, dir_y) = (angle.cos(), angle.sin());
+
+            let distance = |px: f64, py: f64| {
+                let (rel_x, rel_y) = (px - center.0, py - center.1);
+                let along = (rel_x * dir_x + rel_y * dir_y).clamp(-half_length, half_length);
+                ((rel_x - along * dir_x).powi(2) + (rel_y - along * dir_y).powi(2)).sqrt()
+            };
+            paint(pupil, center, half_length + half_width, 0.8, |px, py| distance(px, py) - half_width);
+        }
+    }
+
+    // every wavelength scales the pattern with itself, weighted by the same spectrum as the ghost dispersion
+    fn integrate_wavelengths(&self, power: &[f32], spectrum: &Spectrum) -> Vec<[f32; 3]> {
+        let count = self.wavelengths.max(1);
+        let wavelengths: Vec<_> = (0..count)
+            .map(|idx| {
+                let wavelength = spectrum.wavelength((idx as f32 + 0.5) / count as f32);
+                (BASE_WAVELENGTH / wavelength, spectrum.rgb(wavelength))
+            })
+            .collect();
+
+        let mut weight_sums = [0.0; 3];
+        for (_, weights) in &wavelengths {
+            weight_sums.iter_mut().zip(weights).for_each(|(sum, weight)| *sum += weight);
+        }
+
+        let half = STARBURST_SIZE as f32 / 2.0;
+        let mut out = vec![[0.0; 3]; STARBURST_SIZE * STARBURST_SIZE];
+        for (idx, pixel) in out.iter_mut().enumerate() {
+            let (x, y) = ((idx % STARBURST_SIZE) as f32 + 0.5 - half, (idx / STARBURST_SIZE) as f32 + 0.5 - half);
+
+            for (scale, weights) in &wavelengths {
+                // longer wavelengths spread the same energy over a larger pattern
+                let value = sample(power, x * scale + half, y * scale + half) * scale * scale;
+                for ((channel, weight), sum) in pixel.iter_mut().zip(weights).zip(&weight_sums) {
+                    *channel += value * weight / sum.max(1e-6) * GAIN;
+                }
+            }
+
+            pixel.iter_mut().for_each(|channel| *channel /= 1.0 + *channel / CENTER_LIMIT);
+        }
+
+        out
+    }
+}
+
+impl Default for Starburst {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// transmission of the aperture, antialiased over a pixel at its edge
+fn rasterize_aperture(shape: ApertureShape) -> Vec<f64> {
+    let edge = edge_radii(shape);
+
+    (0..STARBURST_SIZE * STARBURST_SIZE)
+        .map(|idx| {
+            let (x, y) = pupil_coords(idx % STARBURST_SIZE, idx / STARBURST_SIZE);
+            let angle = y.atan2(x).rem_euclid(2.0 * PI);
+            let radius = edge[(angle / (2.0 * PI) * EDGE_TABLE_LEN as f64) as usize % EDGE_TABLE_LEN];
+
+            ((radius - (x * x + y * y).sqrt()) * PUPIL_RADIUS + 0.5).clamp(0.0, 1.0)
+        })
+        .collect()
+}
+
+// distance of the aperture rim from its center, for evenly spaced angles
+fn edge_radii(shape: ApertureShape) -> Vec<f64> {
+    if shape == ApertureShape::Circular {
+        return vec![1.0; EDGE_TABLE_LEN];
+    }
+
+    // the first vertex of the fan is its center
+    let rim: Vec<_> = ghost::ghost_geo_vertices(shape)
+        .chunks_exact(3)
+        .skip(1)
+        .map(|v| (v[0] as f64, v[1] as f64))
+        .collect();
+
+    (0..EDGE_TABLE_LEN)
+        .map(|idx| {
+            let angle = (idx as f64 + 0.5) / EDGE_TABLE_LEN as f64 * 2.0 * PI;
+            let dir = (angle.cos(), angle.sin());
+
+            rim.windows(2)
+                .filter_map(|segment| {
+                    let (start, end) = (segment[0], segment[1]);
+                    let edge = (end.0 - start.0, end.1 - start.1);
+                    let denom = dir.0 * edge.1 - dir.1 * edge.0;
+                    if denom.abs() < 1e-12 {
+                        return None;
+                    }
+
+                    let along_ray = (start.0 * edge.1 - start.1 * edge.0) / denom;
+                    let along_edge = (start.0 * dir.1 - start.1 * dir.0) / denom;
+                    (along_ray > 0.0 && (0.0..=1.0).contains(&along_edge)).then_some(along_ray)
+                })
+                .fold(0.0, f64::max)
+        })
+        .collect()
+}
+
+fn pupil_coords(x: usize, y: usize) -> (f64, f64) {
+    let half = STARBURST_SIZE as f64 / 2.0;
+    ((x as f64 + 0.5 - half) / PUPIL_RADIUS, (y as f64 + 0.5 - half) / PUPIL_RADIUS)
+}
+
+fn random_in_disc(rng: &mut SplitMix64) -> (f64, f64) {
+    let radius = (rng.next_f32() as f64).sqrt();
+    let angle = rng.next_f32() as f64 * 2.0 * PI;
+    (radius * angle.cos(), radius * angle.sin())
+}
+
+// darkens the pupil by `opacity` where `distance` is negative, within `extent` of `center`
+fn paint<F: Fn(f64, f64) -> f64>(pupil: &mut [f64], center: (f64, f64), extent: f64, opacity: f64, distance: F) {
+    let half = STARBURST_SIZE as f64 / 2.0;
+    let to_pixel = |coord: f64| ((coord * PUPIL_RADIUS + half) as isize).clamp(0, STARBURST_SIZE as isize - 1) as usize;
+
+    for y in to_pixel(center.1 - extent - 0.1)..=to_pixel(center.1 + extent + 0.1) {
+        for x in to_pixel(center.0 - extent - 0.1)..=to_pixel(center.0 + extent + 0.1) {
+            let (px, py) = pupil_coords(x, y);
+            let coverage = (0.5 - distance(px, py) * PUPIL_RADIUS).clamp(0.0, 1.0);
+            pupil[y * STARBURST_SIZE + x] *= 1.0 - opacity * coverage;
+        }
+    }
+}
+
+// squared magnitude of the 2D FFT, centered and relative to the center
+fn power_spectrum(pupil: &[f64]) -> Vec<f32> {
+    let mut data: Vec<_> = pupil.iter().map(|value| (*value, 0.0)).collect();
+    let twiddles = twiddles();
+
+    for row in data.chunks_exact_mut(STARBURST_SIZE) {
+        fft(row, &twiddles);
+    }
+
+    let mut column = vec![(0.0, 0.0); STARBURST_SIZE];
+    for x in 0..STARBURST_SIZE {
+        column.iter_mut().enumerate().for_each(|(y, value)| *value = data[y * STARBURST_SIZE + x]);
+        fft(&mut column, &twiddles);
+        column.iter().enumerate().for_each(|(y, value)| data[y * STARBURST_SIZE + x] = *value);
+    }
+
+    let center = data[0].0 * data[0].0 + data[0].1 * data[0].1;
+    let half = STARBURST_SIZE / 2;
+
+    (0..STARBURST_SIZE * STARBURST_SIZE)
+        .map(|idx| {
+            let (x, y) = ((idx % STARBURST_SIZE + half) % STARBURST_SIZE, (idx / STARBURST_SIZE + half) % STARBURST_SIZE);
+            let (re, im) = data[y * STARBURST_SIZE + x];
+            match center > 0.0 {
+                true => ((re * re + im * im) / center) as f32,
+                false => 0.0,
+            }
+        })
+        .collect()
+}
+
+fn twiddles() -> Vec<(f64, f64)> {
+    (0..STARBURST_SIZE / 2)
+        .map(|idx| {
+            let angle = -2.0 * PI * idx as f64 / STARBURST_SIZE as f64;
+            (angle.cos(), angle.sin())
+        })
+        .collect()
+}
+
+// in-place radix-2 FFT of `STARBURST_SIZE` values, with the factors from `twiddles`
+fn fft(data: &mut [(f64, f64)], twiddles: &[(f64, f64)]) {
+    let len = data.len();
+
+    let mut reversed = 0;
+    for idx in 1..len {
+        let mut bit = len >> 1;
+        while reversed & bit != 0 {
+            reversed ^= bit;
+            bit >>= 1;
+        }
+        reversed |= bit;
+
+        if idx < reversed {
+            data.swap(idx, reversed);
+        }
+    }
+
+    let mut span = 2;
+    while span <= len {
+        let stride = len / span;
+        for start in (0..len).step_by(span) {
+            for k in 0..span / 2 {
+                let (tw_re, tw_im) = twiddles[k * stride];
+                let (a, b) = (data[start + k], data[start + k + span / 2]);
+                let rotated = (b.0 * tw_re - b.1 * tw_im, b.0 * tw_im + b.1 * tw_re);
+
+                data[start + k] = (a.0 + rotated.0, a.1 + rotated.1);
+                data[start + k + span / 2] = (a.0 - rotated.0, a.1 - rotated.1);
+            }
+        }
+        span <<= 1;
+    }
+}
+
+// bilinear, zero outside of the pattern
+fn sample(power: &[f32], x: f32, y: f32) -> f32 {
+    let size = STARBURST_SIZE as f32;
+    sampling::bilinear((STARBURST_SIZE, STARBURST_SIZE), (x / size, y / size), Edge::Zero, |x, y| {
+        power[y * STARBURST_SIZE + x]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const N: usize = STARBURST_SIZE;
+
+    fn transformed(mut data: Vec<(f64, f64)>) -> Vec<(f64, f64)> {
+        fft(&mut data, &twiddles());
+        data
+    }
+
+    // the inverse through the forward transform of the conjugate
+    fn inverse(data: &[(f64, f64)]) -> Vec<(f64, f64)> {
+        let conjugated = transformed(data.iter().map(|(re, im)| (*re, -im)).collect());
+        conjugated.iter().map(|(re, im)| (re / N as f64, -im / N as f64)).collect()
+    }
+
+    fn assert_close(actual: (f64, f64), expected: (f64, f64), what: &str) {
+        assert!(
+            (actual.0 - expected.0).abs() < 1e-9 && (actual.1 - expected.1).abs() < 1e-9,
+            "{}: {:?}, expected {:?}",
+            what,
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn impulse_gives_flat_spectrum() {
+        let mut impulse = vec![(0.0, 0.0); N];
+        impulse[0] = (1.0, 0.0);
+
+        for (idx, value) in transformed(impulse).into_iter().enumerate() {
+            assert_close(value, (1.0, 0.0), &format!("bin {}", idx));
+        }
+    }
+
+    #[test]
+    fn dc_gives_single_bin() {
+        for (idx, value) in transformed(vec![(1.0, 0.0); N]).into_iter().enumerate() {
+            let expected = if idx == 0 { N as f64 } else { 0.0 };
+            assert_close(value, (expected, 0.0), &format!("bin {}", idx));
+        }
+    }
+
+    #[test]
+    fn matches_direct_dft() {
+        let mut rng = SplitMix64(7);
+        let signal: Vec<_> = (0..N).map(|_| (rng.next_f32() as f64 - 0.5, rng.next_f32() as f64 - 0.5)).collect();
+        let spectrum = transformed(signal.clone());
+
+        for bin in [0, 1, 5, N / 2 - 1, N / 2, N - 3] {
+            let expected = signal.iter().enumerate().fold((0.0, 0.0), |(re, im), (idx, (x_re, x_im))| {
+                let angle = -2.0 * PI * (bin * idx) as f64 / N as f64;
+                (re + x_re * angle.cos() - x_im * angle.sin(), im + x_re * angle.sin() + x_im * angle.cos())
+            });
+            assert_close(spectrum[bin], expected, &format!("bin {}", bin));
+        }
+    }
+
+    #[test]
+    fn round_trip_recovers_input() {
+        let mut rng = SplitMix64(3);
+        let signal: Vec<_> = (0..N).map(|_| (rng.next_f32() as f64, rng.next_f32() as f64)).collect();
+
+        for (idx, (value, expected)) in inverse(&transformed(signal.clone())).into_iter().zip(signal).enumerate() {
+            assert_close(value, expected, &format!("sample {}", idx));
+        }
+    }
+}
@@ -0,0 +1,92 @@
+//! Compares the CPU reference renderer against the GL renderer, skipped where no EGL context can be created.
+
+use lensflaregen::{cpu::CpuRenderer, effect::Effect, headless::HeadlessRenderer};
+
+const SIZE: (u32, u32) = (160, 90);
+// the GL buffer keeps only 6 or 5 mantissa bits per channel, and its aperture atlas is rasterized slightly differently
+const ABSOLUTE_TOLERANCE: f32 = 0.02;
+const RELATIVE_TOLERANCE: f32 = 0.02;
+
+/// Renders `effect` with both backends, `None` when there is no GL context to render with.
+fn render_both(effect: &Effect) -> Option<(Vec<f32>, Vec<f32>)> {
+    let mut gl = match HeadlessRenderer::with_size(SIZE.0, SIZE.1) {
+        Ok(gl) => gl,
+        Err(err) => {
+            eprintln!("skipping the GL comparison: {}", err);
+            return None;
+        }
+    };
+
+    let gl = gl.render(effect, effect.lights[0].position(), SIZE);
+    let cpu = CpuRenderer::new().unwrap().render(effect, SIZE);
+    assert_eq!(gl.len(), cpu.len());
+
+    Some((gl, cpu))
+}
+
+/// Checks every channel of every pixel against `ABSOLUTE_TOLERANCE` plus `RELATIVE_TOLERANCE` of the GL value.
+fn assert_within_tolerance(gl: &[f32], cpu: &[f32]) {
+    for (idx, (gl, cpu)) in gl.chunks_exact(3).zip(cpu.chunks_exact(3)).enumerate() {
+        for c in 0..3 {
+            let tolerance = ABSOLUTE_TOLERANCE + RELATIVE_TOLERANCE * gl[c].abs();
+            assert!(
+                (gl[c] - cpu[c]).abs() <= tolerance,
+                "channel {} of pixel ({}, {}) is {} on the GPU and {} on the CPU",
+                c,
+                idx % SIZE.0 as usize,
+                idx / SIZE.0 as usize,
+                gl[c],
+                cpu[c]
+            );
+        }
+    }
+}
+
+/// Mean absolute difference of every channel, relative to the mean value of that channel in `expected`.
+fn relative_channel_error(expected: &[f32], actual: &[f32]) -> [f32; 3] {
+    let mut error = [0.0; 3];
+    let mut total = [0.0; 3];
+    for (expected, actual) in expected.chunks_exact(3).zip(actual.chunks_exact(3)) {
+        for c in 0..3 {
+            error[c] += (expected[c] - actual[c]).abs();
+            total[c] += expected[c].abs();
+        }
+    }
+
+    [0, 1, 2].map(|c| error[c] / total[c].max(f32::EPSILON))
+}
+
+#[test]
+fn default_effect_matches_gl() {
+    let effect = Effect::new();
+    let (gl, cpu) = match render_both(&effect) {
+        Some(buffers) => buffers,
+        None => return,
+    };
+
+    for (channel, error) in relative_channel_error(&gl, &cpu).iter().enumerate() {
+        assert!(*error < 0.05, "channel {} differs by {:.4} of its mean", channel, error);
+    }
+}
+
+#[test]
+fn ghosts_across_frame_edge_match_gl() {
+    let mut effect = Effect::new();
+    effect.flare.intensity = 0.0;
+    effect.flare.ray_intensity = 0.0;
+    for ghost in &mut effect.ghosts {
+        ghost.dispersion = 0.2;
+    }
+    // pushes the large ghosts over the bottom left edge, where a wrapping lookup would show them on the opposite side
+    effect.lights[0].set_position((0.95, 0.9));
+
+    let (gl, cpu) = match render_both(&effect) {
+        Some(buffers) => buffers,
+        None => return,
+    };
+
+    assert_within_tolerance(&gl, &cpu);
+    for (channel, error) in relative_channel_error(&gl, &cpu).iter().enumerate() {
+        assert!(*error < 0.02, "channel {} differs by {:.4} of its mean", channel, error);
+    }
+}
@@ -0,0 +1,321 @@
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::{anyhow, bail, Context as _, Result};
+use image::{codecs::hdr::HdrEncoder, ColorType, Rgb};
+use structopt::StructOpt;
+
+use lensflaregen::{
+    animation::Track,
+    aperture::ApertureImage,
+    background::Background,
+    cpu::{self, CpuRenderer},
+    effect::Effect,
+    export::{self, ExrOptions, ExrPrecision},
+    headless::HeadlessRenderer,
+    occlusion::{MaskChannel, OcclusionMask},
+    preset::Preset,
+};
+
+#[derive(StructOpt)]
+#[structopt(name = "lfg", about = "Lens flare generator batch renderer")]
+enum Command {
+    /// Renders a preset into an image file
+    Render(RenderArgs),
+}
+
+#[derive(StructOpt)]
+struct RenderArgs {
+    /// Preset file, the default effect is rendered when omitted
+    #[structopt(short, long)]
+    preset: Option<PathBuf>,
+
+    /// Output image, `.exr` or `.hdr` for scene-linear data, any other supported format for tonemapped output.
+    /// A run of `#` in the file name is replaced by the zero padded frame number
+    #[structopt(short, long)]
+    output: PathBuf,
+
+    /// Background plate added under the flare before tonemapping, `.exr` and `.hdr` plates are read as scene-linear
+    #[structopt(long)]
+    background: Option<PathBuf>,
+
+    /// Grayscale aperture image every ghost is shaped by, instead of the preset's aperture shape
+    #[structopt(long)]
+    aperture: Option<PathBuf>,
+
+    /// Occlusion mask scaling the light intensities by their visible fraction.
+    /// A run of `#` in the file name is replaced by the frame number, for per-frame mask sequences
+    #[structopt(long)]
+    occlusion: Option<PathBuf>,
+
+    /// Mask channel holding the light visibility
+    #[structopt(long, default_value = "alpha", possible_values = &["alpha", "luminance"])]
+    occlusion_channel: MaskChannel,
+
+    /// Treats the mask as white where the light is blocked
+    #[structopt(long)]
+    occlusion_invert: bool,
+
+    /// Radius of the sampled light disc relative to the frame height, overrides the preset value
+    #[structopt(long)]
+    occlusion_radius: Option<f32>,
+
+    /// Animation frame, or an inclusive `start-end` range rendered as an image sequence
+    #[structopt(long, parse(try_from_str = parse_frames))]
+    frames: Option<FrameRange>,
+
+    /// Position of the first light as `x,y`, from `0,0` in the bottom left to `1,1` in the top right corner
+    #[structopt(long, parse(try_from_str = parse_position))]
+    position: Option<(f32, f32)>,
+
+    /// Output resolution as `WIDTHxHEIGHT`
+    #[structopt(long, default_value = "1920x1080", parse(try_from_str = parse_size))]
+    size: (u32, u32),
+
+    /// Dispersion sample count, overrides the preset value
+    #[structopt(long)]
+    samples: Option<u16>,
+
+    /// Tonemap mode, overrides the preset value
+    #[structopt(long, possible_values = &["exposure", "clamp"])]
+    tonemap: Option<TonemapMode>,
+
+    /// Skips tonemapping and writes scene-linear data
+    #[structopt(long)]
+    linear: bool,
+
+    /// Stores EXR output as half instead of full floats
+    #[structopt(long)]
+    half: bool,
+
+    /// Adds an alpha channel to EXR output
+    #[structopt(long)]
+    alpha: bool,
+
+    /// Renders with the CPU reference renderer instead of OpenGL
+    #[structopt(long)]
+    cpu: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum TonemapMode {
+    Exposure,
+    Clamp,
+}
+
+impl FromStr for TonemapMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "exposure" => Ok(TonemapMode::Exposure),
+            "clamp" => Ok(TonemapMode::Clamp),
+            _ => Err(format!("unknown tonemap mode '{}'", s)),
+        }
+    }
+}
+
+fn main() -> Result<()> {
+    match Command::from_args() {
+        Command::Render(args) => render(args),
+    }
+}
+
+fn render(args: RenderArgs) -> Result<()> {
+    let mut preset = match &args.preset {
+        Some(path) => Preset::load(path).with_context(|| format!("Failed to load preset {}", path.display()))?,
+        None => Preset::new("default", Effect::new()),
+    };
+
+    if let Some(position) = args.position {
+        preset.effect.set_position(position);
+        if let Some(tracks) = preset.animation.lights.first_mut() {
+            tracks.position = Track::default();
+        }
+    }
+    if let Some(samples) = args.samples {
+        preset.effect.samples = samples;
+    }
+    if let Some(radius) = args.occlusion_radius {
+        preset.effect.occlusion_radius = radius;
+    }
+    if let Some(mode) = args.tonemap {
+        preset.effect.tonemap = match mode {
+            TonemapMode::Exposure => true,
+            TonemapMode::Clamp => false,
+        };
+    }
+    preset.validate()?;
+
+    let has_extension = |expected: &str| args.output.extension().is_some_and(|ext| ext.eq_ignore_ascii_case(expected));
+    let is_exr = has_extension("exr");
+    if args.linear != (is_exr || has_extension("hdr")) {
+        bail!("Scene-linear output requires the `--linear` flag together with an `.exr` or `.hdr` output file");
+    }
+    if (args.half || args.alpha) && !is_exr {
+        bail!("`--half` and `--alpha` are only supported for `.exr` output");
+    }
+
+    let frames = args.frames.unwrap_or(FrameRange { start: 0, end: 0 });
+    if frames.start != frames.end && !args.output.file_name().is_some_and(|name| name.to_string_lossy().contains('#')) {
+        bail!("Rendering a frame range requires a `#` frame number placeholder in the output file name");
+    }
+
+    let background = match &args.background {
+        Some(path) => Some(Background::load(path).with_context(|| format!("Failed to load background {}", path.display()))?),
+        None => None,
+    };
+
+    let aperture = match &args.aperture {
+        Some(path) => Some(ApertureImage::load(path).with_context(|| format!("Failed to load aperture image {}", path.display()))?),
+        None => None,
+    };
+
+    let mut backend = match args.cpu {
+        true => {
+            let mut cpu = CpuRenderer::new()?;
+            cpu.set_background(background);
+            cpu.set_aperture_image(aperture);
+            Backend::Cpu(cpu)
+        }
+        false => {
+            let mut headless = HeadlessRenderer::with_size(args.size.0, args.size.1)?;
+            headless.renderer_mut().set_background(background.as_ref());
+            headless.renderer_mut().set_aperture_image(aperture.as_ref());
+            Backend::Gpu(headless)
+        }
+    };
+
+    let mut occlusion: Option<(PathBuf, OcclusionMask)> = None;
+
+    for frame in frames.start..=frames.end {
+        let mut effect = preset.effect_at(frame as f32);
+        let output = frame_path(&args.output, frame);
+
+        if let Some(pattern) = &args.occlusion {
+            let path = frame_path(pattern, frame);
+            if occlusion.as_ref().is_none_or(|(loaded, _)| loaded != &path) {
+                let mask = OcclusionMask::load(&path, args.occlusion_channel, args.occlusion_invert)
+                    .with_context(|| format!("Failed to load occlusion mask {}", path.display()))?;
+                occlusion = Some((path, mask));
+            }
+        }
+        if let Some((_, mask)) = &occlusion {
+            mask.apply(&mut effect, args.size);
+        }
+
+        let (hdr, ldr) = backend.render(&effect, args.size);
+        write_output(&args, &output, &Preset::new(preset.name.as_str(), effect), &hdr, &ldr)
+            .with_context(|| format!("Failed to write {}", output.display()))?;
+    }
+
+    Ok(())
+}
+
+enum Backend {
+    Cpu(CpuRenderer),
+    Gpu(HeadlessRenderer),
+}
+
+impl Backend {
+    /// Returns the linear and the tonemapped image, both with rows ordered from top to bottom.
+    fn render(&mut self, effect: &Effect, size: (u32, u32)) -> (Vec<f32>, Vec<u8>) {
+        match self {
+            Backend::Cpu(cpu) => {
+                let hdr = cpu.render(effect, size);
+                let ldr = cpu::tonemap(&hdr, effect.tonemap);
+                (hdr, ldr)
+            }
+            Backend::Gpu(headless) => {
+                let renderer = headless.renderer_mut();
+                renderer.render(effect);
+                let result = (renderer.read_hdr(), renderer.read_tonemapped(effect.tonemap));
+                renderer.next_frame();
+                result
+            }
+        }
+    }
+}
+
+fn write_output(args: &RenderArgs, output: &Path, preset: &Preset, hdr: &[f32], ldr: &[u8]) -> Result<()> {
+    let (width, height) = args.size;
+
+    if args.linear && output.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("exr")) {
+        let options = ExrOptions {
+            precision: match args.half {
+                true => ExrPrecision::Half,
+                false => ExrPrecision::Full,
+            },
+            alpha: args.alpha,
+        };
+        export::write_exr(output, hdr, args.size, options, preset)?;
+    } else if args.linear {
+        let pixels: Vec<_> = hdr.chunks_exact(3).map(|p| Rgb([p[0], p[1], p[2]])).collect();
+
+        let file = File::create(output)?;
+        HdrEncoder::new(BufWriter::new(file)).encode(&pixels, width as usize, height as usize)?;
+    } else {
+        image::save_buffer(output, ldr, width, height, ColorType::Rgb8)?;
+    }
+
+    Ok(())
+}
+
+/// Replaces the last run of `#` in the file name with the zero padded frame number.
+fn frame_path(pattern: &Path, frame: u32) -> PathBuf {
+    let file_name = pattern.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+
+    let file_name = match file_name.rfind('#') {
+        Some(end) => {
+            let start = file_name[..end].trim_end_matches('#').len();
+            let width = end + 1 - start;
+            format!("{}{:0width$}{}", &file_name[..start], frame, &file_name[end + 1..], width = width)
+        }
+        None => file_name,
+    };
+
+    pattern.with_file_name(file_name)
+}
+
+#[derive(Debug, Clone, Copy)]
+struct FrameRange {
+    start: u32,
+    end: u32,
+}
+
+fn parse_frames(s: &str) -> Result<FrameRange> {
+    let range = match s.split_once('-') {
+        Some((start, end)) => FrameRange {
+            start: start.trim().parse()?,
+            end: end.trim().parse()?,
+        },
+        None => {
+            let frame = s.trim().parse()?;
+            FrameRange { start: frame, end: frame }
+        }
+    };
+    if range.start > range.end {
+        bail!("the first frame has to come before the last one");
+    }
+
+    Ok(range)
+}
+
+fn parse_position(s: &str) -> Result<(f32, f32)> {
+    let (x, y) = s.split_once(',').ok_or_else(|| anyhow!("expected `x,y`"))?;
+    Ok((x.trim().parse()?, y.trim().parse()?))
+}
+
+fn parse_size(s: &str) -> Result<(u32, u32)> {
+    let (width, height) = s.split_once('x').ok_or_else(|| anyhow!("expected `WIDTHxHEIGHT`"))?;
+    let size = (width.trim().parse()?, height.trim().parse()?);
+    if size.0 == 0 || size.1 == 0 {
+        bail!("resolution has to be non-zero");
+    }
+
+    Ok(size)
+}
@@ -8,7 +8,6 @@ use glutin::{
 use simple_logger::SimpleLogger;
 
 pub mod fps_cap;
-pub mod lfg;
 pub mod ui;
 pub mod window;
 pub mod window_state;
@@ -16,19 +15,11 @@ pub mod window_state;
 use fps_cap::FpsCap;
 use gl_wrapper::{
     framebuffer::Framebuffer,
-    geometry,
     state::{Blend, State},
-    texture::{Texture2d, TextureFormat},
-};
-use lfg::{
-    effect::{ApertureShape, Effect},
-    ghost,
-    shader_lib::ShaderLib,
 };
+use lensflaregen::{effect::Effect, renderer::Renderer};
 use window::Window;
 
-const NOISE_BYTES: &[u8] = include_bytes!("../images/noise.png");
-
 const WIDTH: u32 = 1280;
 const HEIGHT: u32 = 720;
 
@@ -38,19 +29,8 @@ fn main() -> Result<()> {
     let window = Window::with_size(WIDTH, HEIGHT);
     let mut fps_cap = FpsCap::with_target_fps(60);
 
-    let shader_lib = ShaderLib::new().context("Shader compilation error")?;
-
-    let mut main_hdr_buf = Framebuffer::hdr(WIDTH, HEIGHT);
-    let mut side_hdr_buf = Framebuffer::hdr(WIDTH, HEIGHT);
-    let quad = geometry::quad();
-
-    let blades = 8;
-
-    let ghost_geo = ghost::gen_ghost_geo(blades);
+    let mut renderer = Renderer::new(WIDTH, HEIGHT).context("Renderer initialization error")?;
     let mut effect = Effect::new();
-    effect.aperture_shape = ApertureShape::from_blade_count(blades as u8)?;
-
-    let noise = texture_from_bytes(NOISE_BYTES)?;
 
     window.run(move |event, _, control_flow, ui, context, state| match event {
         Event::WindowEvent { event, .. } => match event {
@@ -59,8 +39,7 @@ fn main() -> Result<()> {
             }
             WindowEvent::Resized(size) => {
                 State::viewport(0, 0, size.width, size.height);
-                main_hdr_buf.resize(size.width, size.height);
-                side_hdr_buf.resize(size.width, size.height);
+                renderer.resize(size.width, size.height);
                 state.size = (size.width, size.height);
             }
             WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
@@ -82,25 +61,31 @@ fn main() -> Result<()> {
                 match key {
                     Space => state.fps_capped = !state.fps_capped,
                     T => effect.tonemap = !effect.tonemap,
-                    A => {
-                        effect.flare.style = match effect.flare.style {
-                            lfg::flare::FlareStyle::Normal => lfg::flare::FlareStyle::Anamorphic,
-                            lfg::flare::FlareStyle::Anamorphic => lfg::flare::FlareStyle::Normal,
-                        }
-                    }
                     _ => {}
                 }
             }
-            WindowEvent::MouseInput { button, state: el_state, .. } => {
-                if let MouseButton::Left = button {
-                    state.mouse_left_button_pressed = el_state == ElementState::Pressed;
+            WindowEvent::MouseInput {
+                button: MouseButton::Left,
+                state: el_state,
+                ..
+            } => {
+                state.mouse_left_button_pressed = el_state == ElementState::Pressed;
+
+                // pick the light closest to the cursor, it follows the cursor until the button is released
+                if state.mouse_left_button_pressed && !state.ui_hovered {
+                    let aspect_ratio = state.size.0 as f32 / state.size.1 as f32;
+                    if let Some(idx) = nearest_light(&effect, state.relative_cursor(), aspect_ratio) {
+                        state.selected_light = idx;
+                    }
                 }
             }
             _ => {}
         },
         Event::MainEventsCleared => {
             if state.mouse_left_button_pressed && !state.ui_focused {
-                effect.set_position(state.relative_cursor());
+                if let Some(light) = effect.lights.get_mut(state.selected_light) {
+                    light.set_position(state.relative_cursor());
+                }
             }
 
             context.window().request_redraw();
@@ -113,22 +98,20 @@ fn main() -> Result<()> {
                 State::blend(Blend::Enable(gl::ONE, gl::ONE));
             });
 
-            noise.bind(2);
-
-            effect.draw(&shader_lib, &mut main_hdr_buf, &mut side_hdr_buf, &quad, &ghost_geo, &state);
-
-            Framebuffer::draw_with_default(|_fb| {
-                shader_lib.tonemap.bind();
-                shader_lib.tonemap.set_int_uniform("tonemap", [effect.tonemap as i32]);
-                main_hdr_buf.bind_as_color_texture(0);
-
-                quad.draw();
-            });
+            match ui.occlusion_mask() {
+                Some(mask) => {
+                    let mut occluded = effect.clone();
+                    mask.apply(&mut occluded, state.size);
+                    renderer.render(&occluded);
+                }
+                None => renderer.render(&effect),
+            }
+            renderer.tonemap(effect.tonemap);
 
             Framebuffer::bind_default();
 
-            ui.render_frame(context, &mut effect, state);
-            state.frame_num += 1;
+            ui.render_frame(context, &mut effect, &mut renderer, state);
+            renderer.next_frame();
 
             context.swap_buffers().unwrap();
 
@@ -140,9 +123,12 @@ fn main() -> Result<()> {
     });
 }
 
-fn texture_from_bytes(bytes: &[u8]) -> Result<Texture2d> {
-    let img = image::load_from_memory(bytes)?;
-    let buf = img.as_rgba8().unwrap();
-
-    Ok(Texture2d::new(buf.width(), buf.height(), buf.as_flat_samples().samples, TextureFormat::Rgba))
+fn nearest_light(effect: &Effect, (x, y): (f32, f32), aspect_ratio: f32) -> Option<usize> {
+    effect
+        .lights
+        .iter()
+        .map(|light| ((light.pos_x - x) * aspect_ratio).hypot(light.pos_y - y))
+        .enumerate()
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(idx, _)| idx)
 }
@@ -1,15 +1,151 @@
+use std::{convert::TryFrom, path::Path};
+
 use glutin::{event::Event, PossiblyCurrent, WindowedContext};
-use imgui::{im_str, Condition, SliderFlags, StyleColor, Ui};
+use imgui::{im_str, Condition, ImString, SliderFlags, StyleColor, Ui};
 
-use crate::{
-    lfg::{effect::Effect, flare::FlareStyle},
-    window_state::WindowState,
+use lensflaregen::{
+    animation::{Animation, GhostTracks, Interpolation, Keyframe, Track},
+    aperture::ApertureImage,
+    background::Background,
+    effect::{ApertureShape, Effect},
+    export::{self, ExrOptions, ExrPrecision},
+    flare::{FlareStyle, MAX_STREAKS},
+    generator::GhostGenerator,
+    ghost::{DispersionCenter, Ghost, GhostKind, GhostResponse},
+    lens::Prescription,
+    light::Light,
+    occlusion::{MaskChannel, OcclusionMask},
+    preset::Preset,
+    renderer::Renderer,
+    spectrum::SpectralResponse,
+    starburst::{MAX_SCRATCHES, MAX_WAVELENGTHS},
+    LfgError,
 };
 
+use crate::window_state::WindowState;
+
+const APERTURE_ITEMS: [&str; 16] = [
+    "Effect aperture",
+    "Circular",
+    "3 blades",
+    "4 blades",
+    "5 blades",
+    "6 blades",
+    "7 blades",
+    "8 blades",
+    "9 blades",
+    "10 blades",
+    "11 blades",
+    "12 blades",
+    "13 blades",
+    "14 blades",
+    "15 blades",
+    "16 blades",
+];
+
 pub struct ImguiUi {
     imgui: imgui::Context,
     platform: imgui_winit_support::WinitPlatform,
     renderer: imgui_opengl_renderer::Renderer,
+    preset: PresetState,
+    background: BackgroundState,
+    occlusion: OcclusionState,
+    generator: GhostGenerator,
+    lens: LensState,
+    aperture_image: ApertureImageState,
+    aperture: ApertureState,
+}
+
+struct PresetState {
+    path: ImString,
+    status: String,
+    // kept from the last loaded preset, so saving doesn't drop it
+    animation: Animation,
+    exr_half: bool,
+    exr_alpha: bool,
+}
+
+// list change requested from a ghost panel, applied once the ghosts are no longer borrowed
+enum GhostEdit {
+    Add,
+    Duplicate(usize),
+    Remove(usize),
+    /// From an index to another.
+    Move(usize, usize),
+    /// The whole list was replaced, by the generator or a lens, the tracks of the old ghosts no longer apply.
+    Replaced,
+}
+
+impl GhostEdit {
+    // the animation tracks of a preset follow their ghosts
+    fn apply(self, ghosts: &mut Vec<Ghost>, tracks: &mut Vec<GhostTracks>) {
+        if !tracks.is_empty() {
+            tracks.resize_with(ghosts.len(), GhostTracks::default);
+        }
+
+        match self {
+            GhostEdit::Add => ghosts.push(Ghost::new()),
+            GhostEdit::Duplicate(idx) => {
+                ghosts.insert(idx + 1, ghosts[idx].clone());
+                if !tracks.is_empty() {
+                    tracks.insert(idx + 1, tracks[idx].clone());
+                }
+            }
+            GhostEdit::Remove(idx) => {
+                ghosts.remove(idx);
+                if !tracks.is_empty() {
+                    tracks.remove(idx);
+                }
+            }
+            GhostEdit::Move(from, to) => {
+                let ghost = ghosts.remove(from);
+                ghosts.insert(to, ghost);
+                if !tracks.is_empty() {
+                    let moved = tracks.remove(from);
+                    tracks.insert(to, moved);
+                }
+            }
+            GhostEdit::Replaced => tracks.clear(),
+        }
+
+        // ghosts at the end without tracks need no entries
+        while tracks.last().is_some_and(|last| *last == GhostTracks::default()) {
+            tracks.pop();
+        }
+    }
+}
+
+struct ApertureState {
+    // edited blade count, kept while the aperture is circular or the count is invalid
+    blades: i32,
+    status: String,
+}
+
+struct ApertureImageState {
+    path: ImString,
+    status: String,
+}
+
+struct BackgroundState {
+    path: ImString,
+    status: String,
+}
+
+struct LensState {
+    // built-in prescriptions followed by the loaded ones
+    prescriptions: Vec<Prescription>,
+    selected: usize,
+    path: ImString,
+    max_ghosts: u32,
+    status: String,
+}
+
+struct OcclusionState {
+    path: ImString,
+    status: String,
+    luminance: bool,
+    invert: bool,
+    mask: Option<OcclusionMask>,
 }
 
 impl ImguiUi {
@@ -25,12 +161,58 @@ impl ImguiUi {
 
         platform.attach_window(imgui.io_mut(), context.window(), imgui_winit_support::HiDpiMode::Locked(1.0));
 
-        Self { imgui, platform, renderer }
+        let preset = PresetState {
+            path: ImString::new("preset.ron"),
+            status: String::new(),
+            animation: Animation::default(),
+            exr_half: ExrOptions::default().precision == ExrPrecision::Half,
+            exr_alpha: ExrOptions::default().alpha,
+        };
+
+        let background = BackgroundState {
+            path: ImString::new("background.png"),
+            status: String::new(),
+        };
+
+        let occlusion = OcclusionState {
+            path: ImString::new("mask.png"),
+            status: String::new(),
+            luminance: false,
+            invert: false,
+            mask: None,
+        };
+
+        let lens = LensState {
+            prescriptions: Prescription::builtin(),
+            selected: 0,
+            path: ImString::new("lens.ron"),
+            max_ghosts: 8,
+            status: String::new(),
+        };
+
+        Self {
+            imgui,
+            platform,
+            renderer,
+            preset,
+            background,
+            occlusion,
+            generator: GhostGenerator::new(),
+            lens,
+            aperture_image: ApertureImageState {
+                path: ImString::new("aperture.png"),
+                status: String::new(),
+            },
+            aperture: ApertureState {
+                blades: 8,
+                status: String::new(),
+            },
+        }
     }
 
     pub fn handle_events(&mut self, context: &WindowedContext<PossiblyCurrent>, event: &Event<()>) {
-        let mut io = self.imgui.io_mut();
-        self.platform.handle_event(&mut io, context.window(), event);
+        let io = self.imgui.io_mut();
+        self.platform.handle_event(io, context.window(), event);
     }
 
     pub fn prepare_frame(&mut self, context: &WindowedContext<PossiblyCurrent>) {
@@ -38,98 +220,714 @@ impl ImguiUi {
         self.platform.prepare_frame(io, context.window()).expect("Failed to start frame");
     }
 
-    pub fn render_frame(&mut self, context: &WindowedContext<PossiblyCurrent>, effect: &mut Effect, state: &mut WindowState) {
+    pub fn render_frame(&mut self, context: &WindowedContext<PossiblyCurrent>, effect: &mut Effect, renderer: &mut Renderer, state: &mut WindowState) {
         let ui = self.imgui.frame();
 
         state.ui_focused = ui.is_any_item_active();
+        state.ui_hovered = ui.io().want_capture_mouse;
 
+        let preset = &mut self.preset;
+        let background = &mut self.background;
+        let occlusion = &mut self.occlusion;
+        let generator = &mut self.generator;
+        let lens = &mut self.lens;
+        let aperture_image = &mut self.aperture_image;
+        let aperture = &mut self.aperture;
+        let mut ghost_edit = None;
         imgui::Window::new(im_str!("Effect settings"))
             .size([400.0, 120.0], Condition::FirstUseEver)
             .build(&ui, || {
-                Self::window_build(&ui, effect);
+                Self::window_build(&ui, effect, renderer, preset, background, occlusion, state);
+                ghost_edit = Self::ghosts_build(&ui, effect, renderer, generator, lens, aperture, aperture_image);
             });
+        if let Some(edit) = ghost_edit {
+            edit.apply(&mut effect.ghosts, &mut preset.animation.ghosts);
+        }
         self.platform.prepare_render(&ui, context.window());
         self.renderer.render(ui);
     }
 
-    fn window_build(ui: &Ui, effect: &mut Effect) {
+    fn window_build(
+        ui: &Ui,
+        effect: &mut Effect,
+        renderer: &mut Renderer,
+        preset: &mut PresetState,
+        background: &mut BackgroundState,
+        occlusion: &mut OcclusionState,
+        state: &mut WindowState,
+    ) {
         use imgui::{ColorEdit, EditableColor, Slider};
 
         ui.text(format!("FPS: {}", ui.io().framerate));
 
-        if imgui::CollapsingHeader::new(im_str!("Effect")).default_open(true).build(&ui) {
-            Slider::new(im_str!("Samples")).range(1..=128).build(&ui, &mut effect.samples);
+        if imgui::CollapsingHeader::new(im_str!("Preset")).default_open(true).build(ui) {
+            ui.input_text(im_str!("Path"), &mut preset.path).resize_buffer(true).build();
+
+            if ui.button(im_str!("Save"), [0.0, 0.0]) {
+                let path = preset.path.to_str();
+                let name = Path::new(path).file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+
+                let saved = Preset {
+                    animation: preset.animation.clone(),
+                    ..Preset::new(name, effect.clone())
+                };
+                preset.status = match saved.save(path) {
+                    Ok(()) => format!("Saved to {}", path),
+                    Err(e) => e.to_string(),
+                };
+            }
+            ui.same_line(0.0);
+            if ui.button(im_str!("Load"), [0.0, 0.0]) {
+                preset.status = match Preset::load(preset.path.to_str()) {
+                    Ok(loaded) => {
+                        *effect = loaded.effect;
+                        preset.animation = loaded.animation;
+                        format!("Loaded {}", loaded.name)
+                    }
+                    Err(e) => e.to_string(),
+                };
+            }
+
+            if ui.button(im_str!("Export EXR"), [0.0, 0.0]) {
+                let path = Path::new(preset.path.to_str()).with_extension("exr");
+                let name = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+                let options = ExrOptions {
+                    precision: match preset.exr_half {
+                        true => ExrPrecision::Half,
+                        false => ExrPrecision::Full,
+                    },
+                    alpha: preset.exr_alpha,
+                };
+
+                let hdr = renderer.read_hdr();
+                preset.status = match export::write_exr(&path, &hdr, renderer.size(), options, &Preset::new(name, effect.clone())) {
+                    Ok(()) => format!("Exported to {}", path.display()),
+                    Err(e) => e.to_string(),
+                };
+            }
+            ui.same_line(0.0);
+            ui.checkbox(im_str!("Half float"), &mut preset.exr_half);
+            ui.same_line(0.0);
+            ui.checkbox(im_str!("Alpha"), &mut preset.exr_alpha);
+
+            if !preset.status.is_empty() {
+                ui.text_wrapped(&ImString::new(preset.status.as_str()));
+            }
+        }
+
+        if imgui::CollapsingHeader::new(im_str!("Background")).build(ui) {
+            ui.input_text(im_str!("Image"), &mut background.path).resize_buffer(true).build();
+
+            if ui.button(im_str!("Load image"), [0.0, 0.0]) {
+                background.status = match Background::load(background.path.to_str()) {
+                    Ok(plate) => {
+                        renderer.set_background(Some(&plate));
+                        format!("Loaded {}x{} plate", plate.size().0, plate.size().1)
+                    }
+                    Err(e) => e.to_string(),
+                };
+            }
+            ui.same_line(0.0);
+            if ui.button(im_str!("Clear"), [0.0, 0.0]) {
+                renderer.set_background(None);
+                background.status.clear();
+            }
+
+            if !background.status.is_empty() {
+                ui.text_wrapped(&ImString::new(background.status.as_str()));
+            }
+        }
+
+        if imgui::CollapsingHeader::new(im_str!("Occlusion")).build(ui) {
+            ui.input_text(im_str!("Mask"), &mut occlusion.path).resize_buffer(true).build();
+            ui.checkbox(im_str!("Use luminance"), &mut occlusion.luminance);
+            ui.same_line(0.0);
+            ui.checkbox(im_str!("Invert"), &mut occlusion.invert);
+
+            if ui.button(im_str!("Load mask"), [0.0, 0.0]) {
+                let channel = match occlusion.luminance {
+                    true => MaskChannel::Luminance,
+                    false => MaskChannel::Alpha,
+                };
+
+                occlusion.status = match OcclusionMask::load(occlusion.path.to_str(), channel, occlusion.invert) {
+                    Ok(mask) => {
+                        occlusion.mask = Some(mask);
+                        format!("Loaded {}", occlusion.path)
+                    }
+                    Err(e) => e.to_string(),
+                };
+            }
+            ui.same_line(0.0);
+            if ui.button(im_str!("Clear mask"), [0.0, 0.0]) {
+                occlusion.mask = None;
+                occlusion.status.clear();
+            }
+
+            Slider::new(im_str!("Light radius")).range(0.0..=0.2).build(ui, &mut effect.occlusion_radius);
+
+            if let Some(mask) = &occlusion.mask {
+                for (idx, light) in effect.lights.iter().enumerate() {
+                    let visible = mask.visible_fraction(light.position(), effect.occlusion_radius, state.size);
+                    ui.text(format!("Light {} visible: {:.0}%", idx, visible * 100.0));
+                }
+            }
+
+            if !occlusion.status.is_empty() {
+                ui.text_wrapped(&ImString::new(occlusion.status.as_str()));
+            }
+        }
+
+        if imgui::CollapsingHeader::new(im_str!("Effect")).default_open(true).build(ui) {
+            Slider::new(im_str!("Samples")).range(1..=128).build(ui, &mut effect.samples);
+
+            let mut response = effect.spectrum.response.index();
+            if imgui::ComboBox::new(im_str!("Spectral Response")).build_simple(ui, &mut response, &SpectralResponse::NAMES, &|item| ImString::new(*item).into())
+            {
+                if let Some(response) = SpectralResponse::from_index(response) {
+                    effect.spectrum.response = response;
+                }
+            }
+
+            let mut range = effect.spectrum.range;
+            if imgui::Drag::new(im_str!("Wavelengths (nm)"))
+                .range(300.0..=900.0)
+                .speed(1.0)
+                .build_array(ui, &mut range)
+            {
+                // keeps the shortest wavelength first, as presets require
+                effect.spectrum.range = [range[0].min(range[1] - 1.0), range[1].max(range[0] + 1.0)];
+            }
             ui.checkbox(im_str!("Tonemap"), &mut effect.tonemap);
         }
 
-        if imgui::CollapsingHeader::new(im_str!("Flare")).default_open(true).build(&ui) {
-            Slider::new(im_str!("Intensity")).range(0.0..=5.0).build(&ui, &mut effect.flare.intensity);
+        if imgui::CollapsingHeader::new(im_str!("Lights")).default_open(true).build(ui) {
+            for idx in 0..effect.lights.len() {
+                if imgui::Selectable::new(im_str!("Light {}", idx).as_ref())
+                    .selected(state.selected_light == idx)
+                    .build(ui)
+                {
+                    state.selected_light = idx;
+                }
+            }
+
+            if ui.button(im_str!("Add light"), [0.0, 0.0]) {
+                effect.lights.push(Light::at_position((0.5, 0.5)));
+                state.selected_light = effect.lights.len() - 1;
+            }
+            ui.same_line(0.0);
+            if ui.button(im_str!("Remove light"), [0.0, 0.0]) && state.selected_light < effect.lights.len() {
+                effect.lights.remove(state.selected_light);
+                // the animation tracks of a preset follow their lights
+                if state.selected_light < preset.animation.lights.len() {
+                    preset.animation.lights.remove(state.selected_light);
+                }
+                state.selected_light = state.selected_light.min(effect.lights.len().saturating_sub(1));
+            }
+
+            if let Some(light) = effect.lights.get_mut(state.selected_light) {
+                Slider::new(im_str!("Position X")).range(0.0..=1.0).build(ui, &mut light.pos_x);
+                Slider::new(im_str!("Position Y")).range(0.0..=1.0).build(ui, &mut light.pos_y);
+                Slider::new(im_str!("Light Intensity")).range(0.0..=5.0).build(ui, &mut light.intensity);
+                ColorEdit::new(im_str!("Tint"), EditableColor::Float3(&mut light.tint)).build(ui);
+            }
+        }
+
+        if imgui::CollapsingHeader::new(im_str!("Flare")).default_open(true).build(ui) {
+            Slider::new(im_str!("Intensity")).range(0.0..=5.0).build(ui, &mut effect.flare.intensity);
             Slider::new(im_str!("Ray Intensity"))
                 .range(0.0..=5.0)
-                .build(&ui, &mut effect.flare.ray_intensity);
+                .build(ui, &mut effect.flare.ray_intensity);
 
-            Slider::new(im_str!("Size")).range(0.0..=100.0).build(&ui, &mut effect.flare.size);
-            ColorEdit::new(im_str!("Color"), EditableColor::Float4(&mut effect.flare.color)).build(&ui);
+            Slider::new(im_str!("Size")).range(0.0..=100.0).build(ui, &mut effect.flare.size);
+            ColorEdit::new(im_str!("Color"), EditableColor::Float4(&mut effect.flare.color)).build(ui);
 
-            let mut anam = match effect.flare.style {
-                FlareStyle::Normal => false,
-                FlareStyle::Anamorphic => true,
-            };
-            if ui.checkbox(im_str!("Anamorphic flare"), &mut anam) {
-                match anam {
-                    true => effect.flare.style = FlareStyle::Anamorphic,
-                    false => effect.flare.style = FlareStyle::Normal,
+            let mut style = effect.flare.style.index();
+            if imgui::ComboBox::new(im_str!("Style")).build_simple(ui, &mut style, &FlareStyle::NAMES, &|item| ImString::new(*item).into()) {
+                if let Some(style) = FlareStyle::from_index(style) {
+                    effect.flare.style = style;
+                }
+            }
+
+            if effect.flare.style.uses_rotation() {
+                imgui::AngleSlider::new(im_str!("Ray Rotation"))
+                    .range_degrees(-180.0..=180.0)
+                    .build(ui, &mut effect.flare.rotation);
+                ui.checkbox(im_str!("Align to light"), &mut effect.flare.align_to_light);
+            }
+
+            let flare = &mut effect.flare;
+            match flare.style {
+                FlareStyle::Normal => {}
+                FlareStyle::Anamorphic => {
+                    let anamorphic = &mut flare.anamorphic;
+                    Slider::new(im_str!("Streak Length")).range(0.01..=2.0).build(ui, &mut anamorphic.length);
+                    Slider::new(im_str!("Streak Thickness")).range(0.0..=0.1).build(ui, &mut anamorphic.thickness);
+                    Slider::new(im_str!("Edge Softness"))
+                        .range(0.001..=0.1)
+                        .flags(SliderFlags::LOGARITHMIC)
+                        .build(ui, &mut anamorphic.softness);
+                    Slider::new(im_str!("Streak Falloff")).range(0.0..=2.0).build(ui, &mut anamorphic.falloff);
+                    Slider::new(im_str!("Ring Strength")).range(0.0..=2.0).build(ui, &mut anamorphic.ring);
+                    ColorEdit::new(im_str!("Core Color"), EditableColor::Float3(&mut anamorphic.core_color)).build(ui);
+                    ColorEdit::new(im_str!("Tip Color"), EditableColor::Float3(&mut anamorphic.tip_color)).build(ui);
+                }
+                FlareStyle::Glow => {
+                    Slider::new(im_str!("Glow Radius")).range(0.01..=2.0).build(ui, &mut flare.glow.radius);
+                    Slider::new(im_str!("Glow Falloff")).range(0.0..=10.0).build(ui, &mut flare.glow.falloff);
+                }
+                FlareStyle::Streaks => {
+                    let streaks = &mut flare.streaks;
+                    Slider::new(im_str!("Streak Thickness"))
+                        .range(0.0005..=0.05)
+                        .flags(SliderFlags::LOGARITHMIC)
+                        .build(ui, &mut streaks.thickness);
+                    Slider::new(im_str!("Streak Length")).range(0.01..=2.0).build(ui, &mut streaks.length);
+
+                    let mut removed = None;
+                    for (idx, angle) in streaks.angles.iter_mut().enumerate() {
+                        let id = ui.push_id(idx as i32);
+                        imgui::AngleSlider::new(im_str!("Angle")).range_degrees(-180.0..=180.0).build(ui, angle);
+                        ui.same_line(0.0);
+                        if ui.button(im_str!("Remove"), [0.0, 0.0]) {
+                            removed = Some(idx);
+                        }
+                        id.pop(ui);
+                    }
+                    if let Some(idx) = removed {
+                        streaks.angles.remove(idx);
+                    }
+
+                    if streaks.angles.len() < MAX_STREAKS && ui.button(im_str!("Add streak"), [0.0, 0.0]) {
+                        streaks.angles.push(0.0);
+                    }
+                }
+                FlareStyle::Starburst => {
+                    let starburst = &mut flare.starburst;
+                    Slider::new(im_str!("Dust")).range(0.0..=1.0).build(ui, &mut starburst.dust);
+                    Slider::new(im_str!("Scratches")).range(0..=MAX_SCRATCHES).build(ui, &mut starburst.scratches);
+                    Slider::new(im_str!("Wavelengths"))
+                        .range(1..=MAX_WAVELENGTHS)
+                        .build(ui, &mut starburst.wavelengths);
+
+                    let mut seed = starburst.seed as u32 as i32;
+                    if ui.input_int(im_str!("Starburst seed"), &mut seed).build() {
+                        starburst.seed = seed as u32 as u64;
+                    }
+                }
+            }
+        }
+    }
+
+    fn ghosts_build(
+        ui: &Ui,
+        effect: &mut Effect,
+        renderer: &mut Renderer,
+        generator: &mut GhostGenerator,
+        lens: &mut LensState,
+        aperture: &mut ApertureState,
+        aperture_image: &mut ApertureImageState,
+    ) -> Option<GhostEdit> {
+        use imgui::{ColorEdit, EditableColor, Slider};
+
+        let mut edit = None;
+
+        if imgui::CollapsingHeader::new(im_str!("Aperture")).default_open(true).build(ui) {
+            let mut circular = effect.aperture_shape == ApertureShape::Circular;
+            // follow presets and lens changes, unless an invalid count is being edited
+            if !circular && aperture.status.is_empty() {
+                aperture.blades = effect.aperture_shape.get_blade_count() as i32;
+            }
+
+            let mut changed = ui.checkbox(im_str!("Circular aperture"), &mut circular);
+            changed |= ui.input_int(im_str!("Blades"), &mut aperture.blades).build();
+            if changed {
+                let blades = aperture.blades;
+                let shape = match circular {
+                    true => Ok(ApertureShape::Circular),
+                    false => u8::try_from(blades)
+                        .map_err(|_| LfgError::InvalidEffectValue(format!("aperture shape ({} blades)", blades)))
+                        .and_then(|blades| effect.aperture_shape.with_blade_count(blades)),
+                };
+
+                aperture.status = match shape {
+                    Ok(shape) => {
+                        effect.aperture_shape = shape;
+                        String::new()
+                    }
+                    Err(e) => e.to_string(),
+                };
+            }
+
+            if !circular {
+                let mut rotation = effect.aperture_shape.rotation();
+                if imgui::AngleSlider::new(im_str!("Aperture Rotation"))
+                    .range_degrees(-180.0..=180.0)
+                    .build(ui, &mut rotation)
+                {
+                    effect.aperture_shape = match effect.aperture_shape {
+                        ApertureShape::Blades { count, curvature, notch, .. } => ApertureShape::Blades {
+                            count,
+                            curvature,
+                            rotation,
+                            notch,
+                        },
+                        ApertureShape::Polygonal(count) => ApertureShape::Blades {
+                            count,
+                            curvature: 0.0,
+                            rotation,
+                            notch: None,
+                        },
+                        ApertureShape::Circular => ApertureShape::Circular,
+                    };
                 }
             }
+
+            // turns the ghosts only, the flare rays have their own rotation
+            imgui::AngleSlider::new(im_str!("Ghost Rotation"))
+                .range_degrees(-180.0..=180.0)
+                .build(ui, &mut effect.rotation);
+
+            if !aperture.status.is_empty() {
+                ui.text_wrapped(&ImString::new(aperture.status.as_str()));
+            }
         }
 
-        if imgui::CollapsingHeader::new(im_str!("Ghosts")).default_open(true).build(&ui) {
-            for (idx, ghost) in &mut effect.ghosts.iter_mut().enumerate() {
-                Slider::new(im_str!("Intensity {}", idx).as_ref())
-                    .range(0.0..=5.0)
-                    .build(&ui, &mut ghost.intensity);
-                ColorEdit::new(im_str!("Color {}", idx).as_ref(), EditableColor::Float4(&mut ghost.color)).build(&ui);
+        if imgui::CollapsingHeader::new(im_str!("Aperture image")).build(ui) {
+            ui.input_text(im_str!("Aperture path"), &mut aperture_image.path).resize_buffer(true).build();
+
+            if ui.button(im_str!("Load aperture"), [0.0, 0.0]) {
+                aperture_image.status = match ApertureImage::load(aperture_image.path.to_str()) {
+                    Ok(image) => {
+                        renderer.set_aperture_image(Some(&image));
+                        format!("Loaded {}x{} aperture", image.size().0, image.size().1)
+                    }
+                    Err(e) => e.to_string(),
+                };
+            }
+            ui.same_line(0.0);
+            if ui.button(im_str!("Clear aperture"), [0.0, 0.0]) {
+                renderer.set_aperture_image(None);
+                aperture_image.status.clear();
+            }
 
-                Slider::new(im_str!("Size {}", idx).as_ref()).range(0.0..=100.0).build(&ui, &mut ghost.size);
+            if !aperture_image.status.is_empty() {
+                ui.text_wrapped(&ImString::new(aperture_image.status.as_str()));
+            }
+        }
 
-                Slider::new(im_str!("Offset {}", idx).as_ref()).range(-5.0..=5.0).build(&ui, &mut ghost.offset);
+        if imgui::CollapsingHeader::new(im_str!("Ghost generator")).build(ui) {
+            // imgui only edits signed ints, the bits are kept as they are
+            let mut seed = generator.seed as u32 as i32;
+            if ui.input_int(im_str!("Seed"), &mut seed).build() {
+                generator.seed = seed as u32 as u64;
+            }
 
-                Slider::new(im_str!("Perpendicular Offset {}", idx).as_ref())
-                    .range(-5.0..=5.0)
-                    .build(&ui, &mut ghost.perpendicular_offset);
+            Slider::new(im_str!("Ghost count")).range(0..=32).build(ui, &mut generator.count);
+            Slider::new(im_str!("Spread")).range(0.0..=5.0).build(ui, &mut generator.spread);
+            Slider::new(im_str!("Size range")).range(0.0..=100.0).build_array(ui, &mut generator.size_range);
+            Slider::new(im_str!("Dispersion range"))
+                .range(-1.0..=1.0)
+                .build_array(ui, &mut generator.dispersion_range);
 
-                Slider::new(im_str!("Center Transparency {}", idx).as_ref())
-                    .range(0.0..=20.0)
-                    .build(&ui, &mut ghost.center_transparency);
+            for (idx, color) in generator.palette.iter_mut().enumerate() {
+                ColorEdit::new(im_str!("Palette {}", idx).as_ref(), EditableColor::Float4(color)).build(ui);
+            }
+            if ui.button(im_str!("Add color"), [0.0, 0.0]) {
+                generator.palette.push([1.0, 1.0, 1.0, 1.0]);
+            }
+            ui.same_line(0.0);
+            if ui.button(im_str!("Remove color"), [0.0, 0.0]) {
+                generator.palette.pop();
+            }
+
+            if ui.button(im_str!("Generate"), [0.0, 0.0]) {
+                generator.apply(effect);
+                edit = Some(GhostEdit::Replaced);
+            }
+            ui.same_line(0.0);
+            if ui.button(im_str!("Next seed"), [0.0, 0.0]) {
+                generator.seed = generator.seed.wrapping_add(1);
+                generator.apply(effect);
+                edit = Some(GhostEdit::Replaced);
+            }
+        }
+
+        if imgui::CollapsingHeader::new(im_str!("Lens prescription")).build(ui) {
+            imgui::ComboBox::new(im_str!("Lens")).build_simple(ui, &mut lens.selected, &lens.prescriptions, &|prescription| {
+                ImString::new(prescription.name.as_str()).into()
+            });
+
+            ui.input_text(im_str!("Lens path"), &mut lens.path).resize_buffer(true).build();
+            if ui.button(im_str!("Load lens"), [0.0, 0.0]) {
+                match Prescription::load(lens.path.to_str()) {
+                    Ok(prescription) => {
+                        lens.status = format!("Loaded {}", prescription.name);
+                        lens.prescriptions.push(prescription);
+                        lens.selected = lens.prescriptions.len() - 1;
+                    }
+                    Err(e) => lens.status = format!("Load failed: {}", e),
+                }
+            }
+
+            Slider::new(im_str!("Max ghosts")).range(1..=64).build(ui, &mut lens.max_ghosts);
+
+            if ui.button(im_str!("Derive ghosts"), [0.0, 0.0]) {
+                let prescription = &lens.prescriptions[lens.selected];
+                let derived = prescription.apply(effect, lens.max_ghosts as usize);
+                if derived.is_ok() {
+                    edit = Some(GhostEdit::Replaced);
+                }
+                lens.status = match derived.and_then(|_| prescription.focal_length()) {
+                    Ok(focal_length) => format!("{} ghosts, focal length {:.1} mm", effect.ghosts.len(), focal_length),
+                    Err(e) => format!("Derive failed: {}", e),
+                };
+            }
+
+            if !lens.status.is_empty() {
+                ui.text_wrapped(&ImString::new(lens.status.as_str()));
+            }
+        }
+
+        if imgui::CollapsingHeader::new(im_str!("Ghosts")).default_open(true).build(ui) {
+            if ui.button(im_str!("Add ghost"), [0.0, 0.0]) {
+                edit = Some(GhostEdit::Add);
+            }
+
+            // where the first light sits on the response curves
+            let light_polar = effect.lights.first().map(|light| GhostResponse::light_polar(light.position()));
+
+            for (idx, ghost) in effect.ghosts.iter_mut().enumerate() {
+                let id = ui.push_id(idx as i32);
+                if let Some(ghost_edit) = Self::ghost_build(ui, idx, ghost, light_polar) {
+                    edit = Some(ghost_edit);
+                }
+                id.pop(ui);
+            }
+        }
+
+        edit
+    }
 
-                Slider::new(im_str!("Aspect Ratio {}", idx).as_ref())
-                    .range(0.001..=100.0)
+    // collapsible panel of a single ghost, dragging its header onto another one moves the ghost there
+    fn ghost_build(ui: &Ui, idx: usize, ghost: &mut Ghost, light_polar: Option<(f32, f32)>) -> Option<GhostEdit> {
+        use imgui::{ColorEdit, DragDropFlags, DragDropSource, DragDropTarget, EditableColor, Slider};
+
+        let mut edit = None;
+
+        let title = match ghost.name.is_empty() {
+            true => format!("Ghost {} ({})", idx, GhostKind::NAMES[ghost.kind.index()]),
+            false => ghost.name.clone(),
+        };
+        let state = match (ghost.solo, ghost.muted) {
+            (true, _) => " [solo]",
+            (false, true) => " [muted]",
+            (false, false) => "",
+        };
+        let open = imgui::CollapsingHeader::new(im_str!("{}{}###ghost", title, state).as_ref()).build(ui);
+
+        if let Some(tooltip) = DragDropSource::new(im_str!("ghost")).begin_payload(ui, idx) {
+            ui.text(&title);
+            tooltip.end();
+        }
+        if let Some(target) = DragDropTarget::new(ui) {
+            if let Some(Ok(payload)) = target.accept_payload::<usize>(im_str!("ghost"), DragDropFlags::empty()) {
+                edit = Some(GhostEdit::Move(payload.data, idx));
+            }
+            target.pop();
+        }
+
+        if !open {
+            return edit;
+        }
+
+        let mut name = ImString::new(ghost.name.as_str());
+        if ui.input_text(im_str!("Name"), &mut name).resize_buffer(true).build() {
+            ghost.name = name.to_string();
+        }
+
+        ui.checkbox(im_str!("Mute"), &mut ghost.muted);
+        ui.same_line(0.0);
+        ui.checkbox(im_str!("Solo"), &mut ghost.solo);
+        ui.same_line(0.0);
+        if ui.button(im_str!("Duplicate"), [0.0, 0.0]) {
+            edit = Some(GhostEdit::Duplicate(idx));
+        }
+        ui.same_line(0.0);
+        if ui.button(im_str!("Delete"), [0.0, 0.0]) {
+            edit = Some(GhostEdit::Remove(idx));
+        }
+
+        let mut kind = ghost.kind.index();
+        if imgui::ComboBox::new(im_str!("Kind")).build_simple(ui, &mut kind, &GhostKind::NAMES, &|item| ImString::new(*item).into()) {
+            if let Some(kind) = GhostKind::from_index(kind) {
+                ghost.kind = kind;
+            }
+        }
+
+        Slider::new(im_str!("Intensity")).range(0.0..=5.0).build(ui, &mut ghost.intensity);
+        ColorEdit::new(im_str!("Color"), EditableColor::Float4(&mut ghost.color)).build(ui);
+
+        Slider::new(im_str!("Size")).range(0.0..=100.0).build(ui, &mut ghost.size);
+
+        Slider::new(im_str!("Offset")).range(-5.0..=5.0).build(ui, &mut ghost.offset);
+
+        Slider::new(im_str!("Perpendicular Offset"))
+            .range(-5.0..=5.0)
+            .build(ui, &mut ghost.perpendicular_offset);
+
+        match &mut ghost.kind {
+            GhostKind::Aperture => {
+                Slider::new(im_str!("Center Transparency"))
+                    .range(0.0..=20.0)
+                    .build(ui, &mut ghost.center_transparency);
+            }
+            GhostKind::Disc { softness } => {
+                Slider::new(im_str!("Softness")).range(0.0..=1.0).build(ui, softness);
+            }
+            GhostKind::Ring { width } => {
+                Slider::new(im_str!("Ring Width")).range(0.01..=1.0).build(ui, width);
+            }
+            GhostKind::Streak { thickness, falloff } => {
+                Slider::new(im_str!("Streak Thickness"))
+                    .range(0.001..=1.0)
                     .flags(SliderFlags::LOGARITHMIC)
-                    .build(&ui, &mut ghost.aspect_ratio);
+                    .build(ui, thickness);
+                Slider::new(im_str!("Streak Falloff")).range(0.0..=10.0).build(ui, falloff);
+            }
+            GhostKind::Halo { width } => {
+                Slider::new(im_str!("Halo Width")).range(0.01..=0.5).build(ui, width);
+            }
+        }
 
-                Slider::new(im_str!("Distortion {}", idx).as_ref())
-                    .range(0.0..=1.0)
-                    .build(&ui, &mut ghost.distortion);
+        Slider::new(im_str!("Aspect Ratio"))
+            .range(0.001..=100.0)
+            .flags(SliderFlags::LOGARITHMIC)
+            .build(ui, &mut ghost.aspect_ratio);
 
-                Slider::new(im_str!("Dispersion {}", idx).as_ref())
-                    .range(-1.0..=1.0)
-                    .build(&ui, &mut ghost.dispersion);
+        Slider::new(im_str!("Distortion")).range(0.0..=1.0).build(ui, &mut ghost.distortion);
 
-                let mut disp_center = match ghost.dispersion_center {
-                    crate::lfg::ghost::DispersionCenter::Ghost => true,
-                    crate::lfg::ghost::DispersionCenter::Image => false,
+        Slider::new(im_str!("Dispersion")).range(-1.0..=1.0).build(ui, &mut ghost.dispersion);
+
+        let mut disp_center = match ghost.dispersion_center {
+            DispersionCenter::Ghost => true,
+            DispersionCenter::Image => false,
+        };
+
+        if ui.checkbox(im_str!("Disperse from ghost center"), &mut disp_center) {
+            match disp_center {
+                true => ghost.dispersion_center = DispersionCenter::Ghost,
+                false => ghost.dispersion_center = DispersionCenter::Image,
+            }
+        }
+
+        if ghost.kind.uses_aperture() {
+            // effect aperture, circular, then 3 to 16 blades
+            let mut aperture = match ghost.aperture_shape {
+                None => 0,
+                Some(ApertureShape::Circular) => 1,
+                Some(ApertureShape::Polygonal(blades)) | Some(ApertureShape::Blades { count: blades, .. }) => (blades.get() as usize).clamp(3, 16) - 1,
+            };
+            if imgui::ComboBox::new(im_str!("Aperture")).build_simple(ui, &mut aperture, &APERTURE_ITEMS, &|item| ImString::new(*item).into()) {
+                ghost.aperture_shape = match aperture {
+                    0 => None,
+                    1 => Some(ApertureShape::Circular),
+                    _ => ghost
+                        .aperture_shape
+                        .unwrap_or(ApertureShape::Circular)
+                        .with_blade_count(aperture as u8 + 1)
+                        .ok(),
                 };
+            }
+        }
 
-                if ui.checkbox(im_str!("Disperse from ghost center"), &mut disp_center) {
-                    match disp_center {
-                        true => ghost.dispersion_center = crate::lfg::ghost::DispersionCenter::Ghost,
-                        false => ghost.dispersion_center = crate::lfg::ghost::DispersionCenter::Image,
-                    }
+        if let Some(node) = imgui::TreeNode::new(im_str!("Response")).push(ui) {
+            if let Some((distance, angle)) = light_polar {
+                ui.text(format!("Light distance {:.2}, angle {:.2}", distance, angle));
+            }
+
+            let response = &mut ghost.response;
+            let distances = (0.0, 1.5);
+            let angles = (-std::f32::consts::PI, std::f32::consts::PI);
+            Self::curve_build(ui, "Intensity by distance", &mut response.intensity, distances);
+            Self::curve_build(ui, "Size by distance", &mut response.size, distances);
+            Self::curve_build(ui, "Intensity by angle", &mut response.intensity_angle, angles);
+            Self::curve_build(ui, "Size by angle", &mut response.size_angle, angles);
+
+            node.pop(ui);
+        }
+
+        edit
+    }
+
+    // plot of a response curve, with its keys edited as (x, multiplier) pairs
+    fn curve_build(ui: &Ui, label: &str, track: &mut Track<f32>, (min, max): (f32, f32)) {
+        const PLOT_SAMPLES: usize = 64;
+        // keys closer than this are kept apart, a preset can't have two keys at the same time
+        const KEY_GAP: f32 = 0.001;
+
+        let values: Vec<f32> = (0..PLOT_SAMPLES)
+            .map(|idx| {
+                let x = min + (max - min) * idx as f32 / (PLOT_SAMPLES - 1) as f32;
+                track.sample(x).unwrap_or(1.0)
+            })
+            .collect();
+        let scale_max = values.iter().copied().fold(2.0, f32::max);
+        ui.plot_lines(&ImString::new(label), &values)
+            .scale_min(0.0)
+            .scale_max(scale_max)
+            .graph_size([0.0, 60.0])
+            .build();
+
+        let mut changed = false;
+        let mut removed = None;
+        let times: Vec<f32> = track.keys.iter().map(|key| key.time).collect();
+        for (key_idx, key) in track.keys.iter_mut().enumerate() {
+            let mut point = [key.time, key.value];
+            if imgui::Drag::new(im_str!("{} key {}", label, key_idx).as_ref())
+                .range(min.min(0.0)..=max.max(10.0))
+                .speed(0.01)
+                .build_array(ui, &mut point)
+            {
+                // strictly between the neighbouring keys, so the keys keep their order
+                let earliest = key_idx.checked_sub(1).map_or(min, |prev| times[prev] + KEY_GAP);
+                let latest = times.get(key_idx + 1).map_or(max, |next| next - KEY_GAP);
+                if earliest <= latest {
+                    key.time = point[0].clamp(earliest, latest);
                 }
+                key.value = point[1].max(0.0);
+                changed = true;
+            }
+
+            ui.same_line(0.0);
+            if ui.small_button(im_str!("Remove##{} key {}", label, key_idx).as_ref()) {
+                removed = Some(key_idx);
+            }
+        }
+
+        if let Some(key_idx) = removed {
+            track.keys.remove(key_idx);
+        }
 
-                ui.separator();
+        if ui.small_button(im_str!("Add key##{}", label).as_ref()) {
+            // halfway between the last key and the end of the range
+            let time = track.keys.last().map_or(min, |key| (key.time + max) * 0.5);
+            // nothing is added once the last key sits at the end of the range
+            if track.keys.iter().all(|key| (key.time - time).abs() >= KEY_GAP) {
+                let value = track.sample(time).unwrap_or(1.0);
+                track.keys.push(Keyframe::new(time, value, Interpolation::Linear));
+                changed = true;
             }
         }
+
+        if changed {
+            *track = Track::new(std::mem::take(&mut track.keys));
+        }
+    }
+
+    /// Occlusion mask applied to the previewed effect, if one is loaded.
+    pub fn occlusion_mask(&self) -> Option<&OcclusionMask> {
+        self.occlusion.mask.as_ref()
     }
 
     /// Get a mutable reference to the imgui ui's imgui.
@@ -4,8 +4,9 @@ pub struct WindowState {
     pub cursor: (u32, u32),
     pub mouse_left_button_pressed: bool,
     pub ui_focused: bool,
+    pub ui_hovered: bool,
     pub fps_capped: bool,
-    pub frame_num: u64,
+    pub selected_light: usize,
 }
 
 impl WindowState {
@@ -16,7 +17,8 @@ impl WindowState {
             fps_capped: true,
             mouse_left_button_pressed: false,
             ui_focused: false,
-            frame_num: 0,
+            ui_hovered: false,
+            selected_light: 0,
         }
     }
 